@@ -0,0 +1,261 @@
+//! Web server access logs, read as records through an nginx `log_format` layout.
+
+use regex::bytes::Regex;
+use serde_json::{Map, Number, Value};
+
+use crate::error::AccessFormatError;
+use crate::{
+    convert_dt_spec_regex, parse_timestamp, Conversion, DateTimePattern, ParseOptions, ParsedRecord,
+};
+
+/// nginx's default `combined` format, which is also Apache's Combined Log Format.
+pub const COMBINED: &str = r#"$remote_addr - $remote_user [$time_local] "$request" $status $body_bytes_sent "$http_referer" "$http_user_agent""#;
+
+/// The Common Log Format.
+pub const COMMON: &str =
+    r#"$remote_addr - $remote_user [$time_local] "$request" $status $body_bytes_sent"#;
+
+/// Record keys for the nginx variables that have a shorter common name.
+const FIELD_NAMES: &[(&str, &str)] = &[
+    ("remote_addr", "ip"),
+    ("remote_user", "user"),
+    ("request_method", "method"),
+    ("request_uri", "path"),
+    ("server_protocol", "protocol"),
+    ("body_bytes_sent", "bytes"),
+    ("http_referer", "referer"),
+    ("http_user_agent", "user_agent"),
+];
+
+/// Variables that hold the request time, with the format they are written in.
+const TIME_VARIABLES: &[(&str, &str)] = &[
+    ("time_local", "%d/%b/%Y:%H:%M:%S %z"),
+    ("time_iso8601", "%Y-%m-%dT%H:%M:%S%:z"),
+    ("msec", "%s"),
+];
+
+/// An access log layout, written as an nginx `log_format` string such as
+/// `$remote_addr [$time_local] "$request" $status`.
+///
+/// Each line is split into a record keyed by variable name, with common variables renamed:
+/// `$remote_addr` becomes `ip`, `$body_bytes_sent` becomes `bytes`, `$http_user_agent` becomes
+/// `user_agent` and so on, and `$request` is split into `method`, `path` and `protocol`. The
+/// request time is read from `$time_local`, `$time_iso8601` or `$msec` into `time`. Numbers
+/// are kept as numbers and `-` as null.
+#[derive(Debug)]
+pub struct AccessFormat {
+    regex: Regex,
+    /// The variable each capture group holds, in order.
+    variables: Vec<String>,
+    time: Option<(usize, DateTimePattern)>,
+}
+
+impl AccessFormat {
+    /// Compiles `spec`, which is `combined`, `common` or an nginx `log_format` string.
+    pub fn new(spec: &str) -> Result<AccessFormat, AccessFormatError> {
+        let spec = match spec {
+            "combined" => COMBINED,
+            "common" => COMMON,
+            spec => spec,
+        };
+        let format = || spec.to_string();
+        let mut regex = String::from("^");
+        let mut variables = Vec::new();
+        let mut time = None;
+        let mut rest = spec;
+        while !rest.is_empty() {
+            let start = match rest.find('$') {
+                Some(start) => start,
+                None => {
+                    regex.push_str(&regex::escape(rest));
+                    break;
+                }
+            };
+            regex.push_str(&regex::escape(&rest[..start]));
+            rest = &rest[start + 1..];
+            let (name, after) = match rest.strip_prefix('{') {
+                Some(braced) => {
+                    let end = braced
+                        .find('}')
+                        .ok_or_else(|| AccessFormatError::UnclosedBrace { format: format() })?;
+                    (&braced[..end], &braced[end + 1..])
+                }
+                None => {
+                    let end = rest
+                        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                        .unwrap_or(rest.len());
+                    (&rest[..end], &rest[end..])
+                }
+            };
+            if name.is_empty() {
+                return Err(AccessFormatError::MissingName { format: format() });
+            }
+            // A variable runs up to the character that follows it in the format.
+            let value = match after.chars().next() {
+                Some('$') => r"(\S*?)".to_string(),
+                Some(c) => format!("([^{}]*)", regex::escape(&c.to_string())),
+                None => "(.*)".to_string(),
+            };
+            regex.push_str(&value);
+            if time.is_none() {
+                if let Some((_, format)) = TIME_VARIABLES.iter().find(|(var, _)| *var == name) {
+                    let pattern =
+                        convert_dt_spec_regex(format).expect("time variable formats compile");
+                    time = Some((variables.len(), pattern));
+                }
+            }
+            variables.push(name.to_string());
+            rest = after;
+        }
+        if variables.is_empty() {
+            return Err(AccessFormatError::NoVariables { format: format() });
+        }
+        let regex = Regex::new(&regex).map_err(|source| AccessFormatError::Regex {
+            format: format(),
+            source,
+        })?;
+        Ok(AccessFormat {
+            regex,
+            variables,
+            time,
+        })
+    }
+
+    /// Reads `line` into a record and converts its request time, or returns `None` if the
+    /// line does not have this layout.
+    pub fn convert(
+        &self,
+        line: &[u8],
+        options: &ParseOptions,
+        conversion: &Conversion,
+    ) -> Option<ParsedRecord> {
+        let captures = self.regex.captures(line)?;
+        let mut access = ParsedRecord {
+            record: Map::new(),
+            time: None,
+            time_span: 0..0,
+            time_text: String::new(),
+        };
+        for (idx, variable) in self.variables.iter().enumerate() {
+            let value = captures.get(idx + 1)?;
+            let text = String::from_utf8_lossy(value.as_bytes());
+            match &self.time {
+                Some((time_idx, pattern)) if *time_idx == idx => {
+                    let value = match parse_timestamp(&text, pattern, options) {
+                        Some(dt) => {
+                            access.time = Some(dt);
+                            access.time_span = value.range();
+                            access.time_text = conversion.render(&dt, pattern);
+                            conversion.render_field(&dt, pattern)
+                        }
+                        None => text.into_owned(),
+                    };
+                    access
+                        .record
+                        .insert("time".to_string(), Value::String(value));
+                }
+                _ if variable == "request" => {
+                    let parts: Vec<&str> = text.split(' ').collect();
+                    if let [method, path, protocol] = parts[..] {
+                        for (key, part) in
+                            [("method", method), ("path", path), ("protocol", protocol)]
+                        {
+                            access.record.insert(key.to_string(), field_value(part));
+                        }
+                    } else {
+                        access.record.insert(variable.clone(), field_value(&text));
+                    }
+                }
+                _ => {
+                    let key = FIELD_NAMES
+                        .iter()
+                        .find(|(var, _)| var == variable)
+                        .map_or(variable.as_str(), |(_, key)| key);
+                    access.record.insert(key.to_string(), field_value(&text));
+                }
+            }
+        }
+        Some(access)
+    }
+}
+
+/// The record value for a variable: null for `-`, a number if it is one, or else the text.
+fn field_value(text: &str) -> Value {
+    if text == "-" {
+        return Value::Null;
+    }
+    match text.parse::<Number>() {
+        Ok(number) => Value::Number(number),
+        Err(_) => Value::String(text.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OutputFormat, TargetZone};
+
+    fn convert(format: &AccessFormat, line: &str) -> Option<ParsedRecord> {
+        let conversion = Conversion {
+            zone: TargetZone::from_name("UTC").unwrap(),
+            output: OutputFormat::Input,
+        };
+        format.convert(line.as_bytes(), &ParseOptions::default(), &conversion)
+    }
+
+    #[test]
+    fn reads_combined_lines() {
+        let format = AccessFormat::new("combined").unwrap();
+        let line = r#"10.0.0.1 - - [15/Oct/2026:10:00:00 +0200] "GET /a?b=1 HTTP/1.1" 200 512 "-" "curl/8.0""#;
+        let parsed = convert(&format, line).unwrap();
+        let record = &parsed.record;
+        assert_eq!(record["ip"], "10.0.0.1");
+        assert_eq!(record["user"], Value::Null);
+        assert_eq!(record["method"], "GET");
+        assert_eq!(record["path"], "/a?b=1");
+        assert_eq!(record["protocol"], "HTTP/1.1");
+        assert_eq!(record["status"], 200);
+        assert_eq!(record["bytes"], 512);
+        assert_eq!(record["referer"], Value::Null);
+        assert_eq!(record["user_agent"], "curl/8.0");
+        assert_eq!(record["time"], "15/Oct/2026:08:00:00 +0000");
+        assert_eq!(&line[parsed.time_span], "15/Oct/2026:10:00:00 +0200");
+    }
+
+    #[test]
+    fn reads_custom_layouts() {
+        let format =
+            AccessFormat::new(r#"${host}:$server_port $time_iso8601 "$request" $request_time"#)
+                .unwrap();
+        let line = r#"example.com:443 2026-10-15T10:00:00+02:00 "malformed" 0.012"#;
+        let record = convert(&format, line).unwrap().record;
+        assert_eq!(record["host"], "example.com");
+        assert_eq!(record["server_port"], 443);
+        assert_eq!(record["time"], "2026-10-15T08:00:00+00:00");
+        // A request that is not `METHOD PATH PROTOCOL` is kept whole.
+        assert_eq!(record["request"], "malformed");
+        assert_eq!(record["request_time"].to_string(), "0.012");
+    }
+
+    #[test]
+    fn leaves_other_lines_to_be_read_as_text() {
+        let format = AccessFormat::new("common").unwrap();
+        assert!(convert(&format, "2026-10-15T10:00:00Z started").is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_layouts() {
+        assert!(matches!(
+            AccessFormat::new("${host"),
+            Err(AccessFormatError::UnclosedBrace { .. })
+        ));
+        assert!(matches!(
+            AccessFormat::new("$ $status"),
+            Err(AccessFormatError::MissingName { .. })
+        ));
+        assert!(matches!(
+            AccessFormat::new("no variables"),
+            Err(AccessFormatError::NoVariables { .. })
+        ));
+    }
+}
@@ -0,0 +1,99 @@
+//! The optional TOML config file.
+
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::ConfigError;
+use crate::DEFAULT_FORMATS;
+
+/// Settings read from a TOML config file.
+///
+/// ```toml
+/// default-formats = true
+/// template = "{time} {level:upper} {msg}"
+///
+/// [[format]]
+/// format = "%d/%b/%Y:%H:%M:%S %z"
+/// priority = 10
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Whether [`DEFAULT_FORMATS`] are tried after the others; `None` leaves it to the caller.
+    pub default_formats: Option<bool>,
+    /// The `--pretty` layout, see [`crate::pretty::Template`].
+    pub template: Option<String>,
+    /// Formats to try, each with a priority.
+    #[serde(rename = "format")]
+    pub formats: Vec<FormatEntry>,
+}
+
+/// A format from the config file and how it ranks against the others.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormatEntry {
+    /// A strftime format, as given to `--format`.
+    pub format: String,
+    /// Formats with a higher priority are tried first; formats not in the config have 0.
+    #[serde(default)]
+    pub priority: i64,
+}
+
+impl Config {
+    /// Reads the config file at `path`.
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        let text = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        toml::from_str(&text).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// `$XDG_CONFIG_HOME/logzen/config.toml`, falling back to `~/.config`.
+    pub fn default_path() -> Option<PathBuf> {
+        let base = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(base.join("logzen").join("config.toml"))
+    }
+
+    /// Orders `formats`, followed by the configured formats and then [`DEFAULT_FORMATS`]
+    /// unless `default_formats` is false, into the order they are tried in.
+    ///
+    /// Formats are ranked by their configured priority, highest first; formats with the same
+    /// priority keep the order they were listed in. Repeated formats are only kept once.
+    pub fn prioritize<'a>(&'a self, formats: &[&'a str], default_formats: bool) -> Vec<&'a str> {
+        let defaults: &[&str] = if default_formats {
+            &DEFAULT_FORMATS
+        } else {
+            &[]
+        };
+        let mut ordered: Vec<&str> = Vec::new();
+        let listed = formats
+            .iter()
+            .copied()
+            .chain(self.formats.iter().map(|entry| entry.format.as_str()))
+            .chain(defaults.iter().copied());
+        for format in listed {
+            if !ordered.contains(&format) {
+                ordered.push(format);
+            }
+        }
+        ordered.sort_by_key(|format| std::cmp::Reverse(self.priority(format)));
+        ordered
+    }
+
+    fn priority(&self, format: &str) -> i64 {
+        self.formats
+            .iter()
+            .rev()
+            .find(|entry| entry.format == format)
+            .map_or(0, |entry| entry.priority)
+    }
+}
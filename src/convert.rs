@@ -0,0 +1,350 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset};
+use serde_json::{Map, Number, Value};
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::access::AccessFormat;
+use crate::error::OutputFormatError;
+use crate::json::TimeFields;
+use crate::logfmt::{self, Pair};
+use crate::parse::{find_timestamps, ParseOptions, TimestampMatch};
+use crate::pattern::{DateTimePattern, PatternSet};
+use crate::pretty::Pretty;
+use crate::syslog::SyslogFormat;
+use crate::zone::TargetZone;
+
+/// Sortable ISO 8601 rendering used by the `iso` preset and for epochs.
+pub const ISO_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f%:z";
+
+/// How a converted timestamp is rendered back into the line.
+#[derive(Debug, Clone)]
+pub enum OutputFormat {
+    /// Re-use the format the timestamp was matched with.
+    Input,
+    /// A strftime format.
+    Strftime(String),
+    /// RFC 2822, as in email headers.
+    Rfc2822,
+    /// Milliseconds since the Unix epoch.
+    EpochMillis,
+}
+
+impl OutputFormat {
+    /// Parses a strftime format or one of the presets `iso`, `rfc2822`, `human`, `epoch`
+    /// and `epoch-ms`.
+    pub fn from_spec(spec: &str) -> Result<OutputFormat, OutputFormatError> {
+        let format = match spec {
+            "iso" => OutputFormat::Strftime(ISO_FORMAT.to_string()),
+            "rfc2822" => OutputFormat::Rfc2822,
+            "human" => OutputFormat::Strftime("%a %d %b %Y %H:%M:%S %:z".to_string()),
+            "epoch" => OutputFormat::Strftime("%s".to_string()),
+            "epoch-ms" => OutputFormat::EpochMillis,
+            _ => {
+                if StrftimeItems::new(spec).any(|item| item == Item::Error) {
+                    return Err(OutputFormatError {
+                        spec: spec.to_string(),
+                    });
+                }
+                OutputFormat::Strftime(spec.to_string())
+            }
+        };
+        Ok(format)
+    }
+}
+
+/// Everything needed to turn a parsed timestamp into its replacement text.
+#[derive(Debug, Clone)]
+pub struct Conversion {
+    /// The zone timestamps are converted into.
+    pub zone: TargetZone,
+    /// How they are written.
+    pub output: OutputFormat,
+}
+
+impl Conversion {
+    /// Renders `dt` as the replacement for a timestamp matched by `pat`.
+    pub fn render(&self, dt: &DateTime<FixedOffset>, pat: &DateTimePattern) -> String {
+        match &self.output {
+            // An epoch carries no zone to preserve, so spell it out as an ISO timestamp.
+            OutputFormat::Input if pat.epoch => {
+                self.zone.format(dt, &pat.format.replace("%s", ISO_FORMAT))
+            }
+            OutputFormat::Input if pat.is_naive => {
+                let format = if pat.zulu {
+                    &pat.format[..pat.format.len() - 1]
+                } else {
+                    &pat.format
+                };
+                self.zone.format(dt, format!("{}%:z", format).as_str())
+            }
+            OutputFormat::Input => self.zone.format(dt, &pat.format),
+            OutputFormat::Strftime(format) => self.zone.format(dt, format),
+            OutputFormat::Rfc2822 => self.zone.convert(dt).to_rfc2822(),
+            OutputFormat::EpochMillis => dt.timestamp_millis().to_string(),
+        }
+    }
+
+    /// Renders `dt` as the value of a record field that held a timestamp matched by `pat`.
+    ///
+    /// Unlike [`Conversion::render`], a format without a year is written as ISO 8601, so the
+    /// field keeps the inferred year and reads back as a single time.
+    pub fn render_field(&self, dt: &DateTime<FixedOffset>, pat: &DateTimePattern) -> String {
+        match &self.output {
+            OutputFormat::Input if !pat.has_year => self.zone.format(dt, ISO_FORMAT),
+            _ => self.render(dt, pat),
+        }
+    }
+}
+
+/// Finds and converts every timestamp in `line`.
+pub fn find_and_replace_timestamp(
+    line: &[u8],
+    regex_list: &PatternSet,
+    options: &ParseOptions,
+    conversion: &Conversion,
+) -> Vec<u8> {
+    replace_timestamps(
+        line,
+        &find_timestamps(line, regex_list, options),
+        conversion,
+    )
+}
+
+/// Splices the converted form of each match into `line`, leaving every other byte untouched.
+///
+/// An epoch written as a bare JSON value, as in `"time":1697371234`, is quoted if it is no
+/// longer rendered as a number, so the line stays valid JSON.
+pub fn replace_timestamps(
+    line: &[u8],
+    found: &[TimestampMatch],
+    conversion: &Conversion,
+) -> Vec<u8> {
+    let mut output = Vec::with_capacity(line.len());
+    let mut cursor = 0;
+    for m in found {
+        output.extend_from_slice(&line[cursor..m.start]);
+        let rendered = conversion.render(&m.datetime, m.pattern);
+        if m.pattern.epoch && is_json_value(&line[..m.start]) && rendered.parse::<Number>().is_err()
+        {
+            output.extend_from_slice(Value::String(rendered).to_string().as_bytes());
+        } else {
+            output.extend_from_slice(rendered.as_bytes());
+        }
+        cursor = m.end;
+    }
+    output.extend_from_slice(&line[cursor..]);
+    output
+}
+
+/// Whether a value following `prefix` is an unquoted JSON object value, as after `"key":`.
+fn is_json_value(prefix: &[u8]) -> bool {
+    let prefix = match prefix.trim_ascii_end().strip_suffix(b":") {
+        Some(prefix) => prefix,
+        None => return false,
+    };
+    prefix.trim_ascii_end().ends_with(b"\"")
+}
+
+/// How lines are taken apart to find their timestamps.
+#[derive(Debug)]
+pub enum LineFormat {
+    /// Timestamps are found anywhere in the text.
+    Text,
+    /// Lines are JSON records with timestamps in the given fields. Lines that are not JSON
+    /// objects are read as text.
+    Json(TimeFields),
+    /// Lines are logfmt records with timestamps in the given fields. Lines without a
+    /// `key=value` pair are read as text.
+    Logfmt(TimeFields),
+    /// Lines are web server access logs in the given layout. Lines that do not fit it are read
+    /// as text.
+    Access(AccessFormat),
+    /// Lines are RFC 5424 or RFC 3164 syslog messages. Lines that are neither are read as
+    /// text, unless the messages are filtered.
+    Syslog(SyslogFormat),
+}
+
+/// A line taken apart by a fixed layout, such as an access log or syslog line.
+#[derive(Debug)]
+pub struct ParsedRecord {
+    /// The fields of the line.
+    pub record: Map<String, Value>,
+    /// The time of the line, already converted in the record's `time` field.
+    pub time: Option<DateTime<FixedOffset>>,
+    /// Where the time is written in the line.
+    pub time_span: Range<usize>,
+    /// The converted time as it is written back into the line, which differs from the
+    /// record's `time` for formats without a year.
+    pub time_text: String,
+}
+
+/// What a structured record was read from, to write it back the same way.
+enum Source {
+    Json,
+    Logfmt(Vec<Pair>),
+    /// A fixed layout, with the time written at this span and its converted text.
+    Parsed(Range<usize>, String),
+}
+
+/// How converted structured records are written.
+#[derive(Debug)]
+pub enum RecordOutput {
+    /// In the format they were read in, changing only their timestamp fields. JSON keys keep
+    /// their order, and logfmt lines keep their spacing and quoting.
+    Same,
+    /// As JSON objects.
+    Json,
+    /// As logfmt lines, with nested objects flattened into dotted keys.
+    Logfmt,
+    /// As readable text.
+    Pretty(Pretty),
+}
+
+/// A converted line and the time of its first timestamp, if it has one.
+pub type ConvertedLine<'l> = (Option<DateTime<FixedOffset>>, Cow<'l, [u8]>);
+
+/// Converts whole lines according to their [`LineFormat`].
+#[derive(Debug, Clone, Copy)]
+pub struct LineConverter<'a> {
+    /// How lines are read.
+    pub format: &'a LineFormat,
+    /// The timestamp formats to look for.
+    pub regex_list: &'a PatternSet,
+    /// How the timestamps found are converted.
+    pub conversion: &'a Conversion,
+    /// How structured records are written.
+    pub output: &'a RecordOutput,
+}
+
+impl LineConverter<'_> {
+    /// Converts `line`, returning the time of its first timestamp and the converted line,
+    /// which is only copied if it changed, or `None` if the line is filtered out.
+    pub fn convert<'l>(&self, line: &'l [u8], options: &ParseOptions) -> Option<ConvertedLine<'l>> {
+        if let Some(converted) = self.convert_record(line, options) {
+            return converted;
+        }
+        if let LineFormat::Syslog(format) = self.format {
+            if format.filters() {
+                return None;
+            }
+        }
+        let found = find_timestamps(line, self.regex_list, options);
+        Some(match found.first() {
+            Some(m) => (
+                Some(m.datetime),
+                Cow::Owned(replace_timestamps(line, &found, self.conversion)),
+            ),
+            None => (None, Cow::Borrowed(line)),
+        })
+    }
+
+    /// Converts `line` as a structured record, or returns `None` if it is not one. A record
+    /// that is filtered out converts to `Some(None)`.
+    fn convert_record<'l>(
+        &self,
+        line: &'l [u8],
+        options: &ParseOptions,
+    ) -> Option<Option<ConvertedLine<'l>>> {
+        let fixed_layout = |parsed: ParsedRecord| {
+            let time = parsed.time.map(|dt| ("time", dt));
+            let source = Source::Parsed(parsed.time_span, parsed.time_text);
+            (source, parsed.record, time, time.is_some())
+        };
+        let (source, record, time, changed) = match self.format {
+            LineFormat::Text => return None,
+            LineFormat::Json(fields) => {
+                let mut record = match serde_json::from_slice(line) {
+                    Ok(Value::Object(record)) => record,
+                    _ => return None,
+                };
+                let (time, changed) =
+                    fields.convert_fields(&mut record, self.regex_list, options, self.conversion);
+                (Source::Json, record, time, changed)
+            }
+            LineFormat::Logfmt(fields) => {
+                let pairs = logfmt::parse(line)?;
+                let mut record = logfmt::to_record(&pairs);
+                let (time, changed) =
+                    fields.convert_fields(&mut record, self.regex_list, options, self.conversion);
+                (Source::Logfmt(pairs), record, time, changed)
+            }
+            LineFormat::Access(format) => {
+                fixed_layout(format.convert(line, options, self.conversion)?)
+            }
+            LineFormat::Syslog(format) => {
+                let parsed = format.convert(line, options, self.conversion)?;
+                if !format.keeps(&parsed.record) {
+                    return Some(None);
+                }
+                fixed_layout(parsed)
+            }
+        };
+        let text = match (self.output, source) {
+            (RecordOutput::Same, _) if !changed => Cow::Borrowed(line),
+            (RecordOutput::Same, Source::Logfmt(pairs)) => {
+                Cow::Owned(logfmt::splice(line, &pairs, &record))
+            }
+            (RecordOutput::Same, Source::Parsed(span, time)) => {
+                let mut text = Vec::with_capacity(line.len() + time.len());
+                text.extend_from_slice(&line[..span.start]);
+                text.extend_from_slice(time.as_bytes());
+                text.extend_from_slice(&line[span.end..]);
+                Cow::Owned(text)
+            }
+            (RecordOutput::Same, Source::Json) | (RecordOutput::Json, _) => {
+                Cow::Owned(serde_json::to_vec(&record).expect("JSON values serialize"))
+            }
+            (RecordOutput::Logfmt, _) => Cow::Owned(logfmt::write(&record)),
+            (RecordOutput::Pretty(pretty), _) => {
+                Cow::Owned(pretty.render(&record, time.map(|(key, _)| key)))
+            }
+        };
+        Some(Some((time.map(|(_, dt)| dt), text)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_dt_spec_regex;
+
+    fn replace(line: &str, output: OutputFormat) -> String {
+        let patterns = PatternSet::new(vec![convert_dt_spec_regex("%s").unwrap()]);
+        let conversion = Conversion {
+            zone: TargetZone::from_name("UTC").unwrap(),
+            output,
+        };
+        let line = find_and_replace_timestamp(
+            line.as_bytes(),
+            &patterns,
+            &ParseOptions::default(),
+            &conversion,
+        );
+        String::from_utf8(line).unwrap()
+    }
+
+    #[test]
+    fn quotes_epochs_that_were_bare_json_values() {
+        assert_eq!(
+            replace(r#"{"time":1697371234123,"msg":"hi"}"#, OutputFormat::Input),
+            r#"{"time":"2023-10-15T12:00:34.123+00:00","msg":"hi"}"#
+        );
+        assert_eq!(
+            replace(r#"{"ts": "1697371234"}"#, OutputFormat::Input),
+            r#"{"ts": "2023-10-15T12:00:34+00:00"}"#
+        );
+        assert_eq!(
+            replace("ts=1697371234", OutputFormat::Input),
+            "ts=2023-10-15T12:00:34+00:00"
+        );
+    }
+
+    #[test]
+    fn keeps_epochs_rendered_as_numbers_bare() {
+        assert_eq!(
+            replace(r#"{"time":1697371234123}"#, OutputFormat::EpochMillis),
+            r#"{"time":1697371234123}"#
+        );
+    }
+}
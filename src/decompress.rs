@@ -0,0 +1,69 @@
+//! Reading compressed logs, recognised by their magic numbers rather than their names.
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use std::io::{self, prelude::*};
+use xz2::read::XzDecoder;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const BZIP2_MAGIC: &[u8] = b"BZh";
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+/// Longest magic number above.
+const MAGIC_LEN: usize = 6;
+
+/// Compression formats recognised from the first bytes of an input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Plain text.
+    None,
+    /// gzip, as written by `gzip` and logrotate's `compress`.
+    Gzip,
+    /// Zstandard.
+    Zstd,
+    /// bzip2.
+    Bzip2,
+    /// xz.
+    Xz,
+}
+
+impl Compression {
+    fn from_magic(head: &[u8]) -> Compression {
+        if head.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if head.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else if head.starts_with(BZIP2_MAGIC) {
+            Compression::Bzip2
+        } else if head.starts_with(XZ_MAGIC) {
+            Compression::Xz
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Reads the first bytes of `reader` and identifies its compression.
+///
+/// The bytes read are returned so non-seekable inputs can put them back in front of the
+/// rest of the stream.
+pub fn sniff<R: Read>(reader: &mut R) -> io::Result<(Compression, Vec<u8>)> {
+    let mut head = Vec::with_capacity(MAGIC_LEN);
+    reader.take(MAGIC_LEN as u64).read_to_end(&mut head)?;
+    Ok((Compression::from_magic(&head), head))
+}
+
+/// Wraps `reader` in a decoder for `compression`. Concatenated streams, as produced by
+/// appending to a compressed log, are decoded back to back.
+pub fn decoder<R: Read + Send + 'static>(
+    compression: Compression,
+    reader: R,
+) -> io::Result<Box<dyn Read + Send>> {
+    Ok(match compression {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(zstd::Decoder::new(reader)?),
+        Compression::Bzip2 => Box::new(MultiBzDecoder::new(reader)),
+        Compression::Xz => Box::new(XzDecoder::new_multi_decoder(reader)),
+    })
+}
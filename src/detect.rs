@@ -0,0 +1,126 @@
+//! Picking timestamp formats for a log from a sample of its lines.
+
+use std::io::{self, prelude::*};
+
+use crate::{convert_dt_spec_regex, find_timestamps, ParseOptions, PatternSet};
+
+/// A format must supply the timestamps of at least this share of the sampled lines that have
+/// one to be picked, so a stray match in a message does not become a format.
+const MIN_SHARE: f64 = 0.1;
+
+/// Formats tried by [`detect`], each with the kind of log it comes from.
+///
+/// Where two formats match the same text equally well, the one listed first wins, so more
+/// common and more specific formats come first.
+pub const CATALOGUE: &[(&str, &str)] = &[
+    ("ISO 8601 / RFC 3339", "%Y-%m-%dT%H:%M:%S%.f%:z"),
+    ("ISO 8601 UTC", "%Y-%m-%dT%H:%M:%S%.fZ"),
+    ("ISO 8601, offset without colon", "%Y-%m-%dT%H:%M:%S%.f%z"),
+    ("ISO 8601 without offset", "%Y-%m-%dT%H:%M:%S%.f"),
+    ("Apache/nginx access log", "%d/%b/%Y:%H:%M:%S %z"),
+    ("ctime", "%a %b %e %H:%M:%S %Y"),
+    ("date, Java Date.toString", "%a %b %e %H:%M:%S %Z %Y"),
+    ("Apache error log", "%a %b %d %H:%M:%S%.f %Y"),
+    ("nginx error log, Go log package", "%Y/%m/%d %H:%M:%S%.f"),
+    ("syslog (RFC 3164)", "^%b %e %H:%M:%S"),
+    ("Python logging, log4j", "%Y-%m-%d %H:%M:%S,%3f"),
+    ("Go time.String", "%Y-%m-%d %H:%M:%S%.f %z"),
+    ("Ruby, Rails", "%Y-%m-%d %H:%M:%S %z"),
+    ("PostgreSQL", "%Y-%m-%d %H:%M:%S%.f %Z"),
+    ("SQL timestamp with offset", "%Y-%m-%d %H:%M:%S%.f%:z"),
+    ("date and time", "%Y-%m-%d %H:%M:%S%.f"),
+    ("java.util.logging", "%b %d, %Y %I:%M:%S %p"),
+    ("Kubernetes klog", "%m%d %H:%M:%S%.6f"),
+    ("Windows event log", "%m/%d/%Y %I:%M:%S %p"),
+    ("US date and time", "%m/%d/%Y %H:%M:%S"),
+    ("European date and time", "%d/%m/%Y %H:%M:%S"),
+    ("RFC 2822, email", "%a, %d %b %Y %H:%M:%S %z"),
+    ("HTTP date", "%a, %d %b %Y %H:%M:%S %Z"),
+];
+
+/// A catalogue format picked by [`detect`].
+#[derive(Debug, Clone)]
+pub struct Detection {
+    /// The kind of log the format comes from.
+    pub name: &'static str,
+    /// The strftime format, as given to `--format`.
+    pub format: &'static str,
+    /// Sampled lines whose timestamp came from this format.
+    pub lines: usize,
+    /// Sampled lines where the format's regex matched, whether or not the text parsed.
+    pub matched: usize,
+}
+
+/// Picks the formats that best explain the timestamps in `lines`.
+///
+/// Every catalogue format is tried on every line as in [`find_timestamps`], so the longest
+/// match that parses wins. Formats are kept if they supplied the timestamps of a reasonable
+/// share of the lines, and are returned with the most used first; ties go to the format whose
+/// matches parse most often.
+pub fn detect(lines: &[Vec<u8>], options: &ParseOptions) -> Vec<Detection> {
+    let patterns = PatternSet::new(
+        CATALOGUE
+            .iter()
+            .map(|(_, format)| convert_dt_spec_regex(format).expect("catalogue format compiles"))
+            .collect(),
+    );
+    // Samples from several inputs follow each other, so they must not feed year inference.
+    let options = options.clone();
+    let mut used = vec![0; CATALOGUE.len()];
+    let mut matched = vec![0; CATALOGUE.len()];
+    let mut timestamped = 0;
+    for line in lines {
+        options.years.restart();
+        let found = find_timestamps(line, &patterns, &options);
+        if !found.is_empty() {
+            timestamped += 1;
+        }
+        for (idx, pattern) in patterns.patterns().iter().enumerate() {
+            if found.iter().any(|m| std::ptr::eq(m.pattern, pattern)) {
+                used[idx] += 1;
+            }
+            if pattern.regex().is_match(line) {
+                matched[idx] += 1;
+            }
+        }
+    }
+
+    let mut detected: Vec<Detection> = CATALOGUE
+        .iter()
+        .enumerate()
+        .filter(|&(idx, _)| used[idx] > 0 && used[idx] as f64 >= timestamped as f64 * MIN_SHARE)
+        .map(|(idx, &(name, format))| Detection {
+            name,
+            format,
+            lines: used[idx],
+            matched: matched[idx],
+        })
+        .collect();
+    detected.sort_by(|a, b| {
+        let parse_rate = |d: &Detection| d.lines as f64 / d.matched as f64;
+        b.lines
+            .cmp(&a.lines)
+            .then(parse_rate(b).total_cmp(&parse_rate(a)))
+    });
+    detected
+}
+
+type Input = Box<dyn BufRead + Send>;
+
+/// Reads up to `max_lines` lines from `reader` for [`detect`].
+///
+/// Returns the lines together with a reader that yields the whole input again, sampled lines
+/// included, so inputs that can only be read once (such as stdin) still work.
+pub fn sample(mut reader: Input, max_lines: usize) -> io::Result<(Vec<Vec<u8>>, Input)> {
+    let mut head = Vec::new();
+    let mut lines = Vec::new();
+    for _ in 0..max_lines {
+        let start = head.len();
+        if reader.read_until(b'\n', &mut head)? == 0 {
+            break;
+        }
+        let line = &head[start..];
+        lines.push(line.strip_suffix(b"\n").unwrap_or(line).to_vec());
+    }
+    Ok((lines, Box::new(io::Cursor::new(head).chain(reader))))
+}
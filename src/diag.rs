@@ -0,0 +1,60 @@
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How much is reported on stderr. Errors that stop logzen are always reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Nothing but fatal errors.
+    Quiet,
+    /// Warnings about input that could not be handled as asked, such as skipped lines.
+    Normal,
+    /// What each input turned out to be and how it is read.
+    Verbose,
+    /// Internals such as the regex each format compiled to.
+    Debug,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+/// Sets the verbosity for the whole process.
+pub fn set_verbosity(level: Verbosity) {
+    VERBOSITY.store(level as u8, Ordering::Relaxed);
+}
+
+/// Whether messages at `level` are currently shown.
+pub fn enabled(level: Verbosity) -> bool {
+    level as u8 <= VERBOSITY.load(Ordering::Relaxed)
+}
+
+pub fn emit(level: Verbosity, args: fmt::Arguments) {
+    if enabled(level) {
+        eprintln!("logzen: {}", args);
+    }
+}
+
+/// Reports a problem logzen recovered from, unless running quietly.
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        $crate::diag::emit($crate::diag::Verbosity::Normal, format_args!($($arg)*))
+    };
+}
+
+/// Reports what logzen is doing, shown with `-v`.
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::diag::emit($crate::diag::Verbosity::Verbose, format_args!($($arg)*))
+    };
+}
+
+/// Reports internal details, shown with `-vv`.
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::diag::emit($crate::diag::Verbosity::Debug, format_args!($($arg)*))
+    };
+}
+
+/// Reports on stderr that line `line` of `source` was skipped because of `error`.
+pub fn report_skipped_line(source: &str, line: u64, error: &io::Error) {
+    warn!("{}:{}: {}; line skipped", source, line, error);
+}
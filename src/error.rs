@@ -0,0 +1,275 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Why a strftime format could not be compiled into a [`crate::DateTimePattern`].
+///
+/// Positions are byte offsets of the offending specifier within the format.
+#[derive(Debug)]
+pub enum PatternError {
+    /// chrono does not recognise the specifier.
+    InvalidSpec {
+        /// The format as given.
+        format: String,
+        /// The specifier, such as `%Q`.
+        spec: String,
+        /// Where the specifier starts.
+        position: usize,
+    },
+    /// The specifier is valid strftime but cannot be matched in log text.
+    UnsupportedSpec {
+        /// The format as given.
+        format: String,
+        /// The specifier, such as `%U`.
+        spec: String,
+        /// Where the specifier starts.
+        position: usize,
+    },
+    /// The generated regex failed to compile.
+    Regex {
+        /// The format as given.
+        format: String,
+        /// The regex error.
+        source: regex::Error,
+    },
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatternError::InvalidSpec {
+                format,
+                spec,
+                position,
+            } => write!(
+                f,
+                "invalid format {:?}: unknown specifier {} at position {}",
+                format, spec, position
+            ),
+            PatternError::UnsupportedSpec {
+                format,
+                spec,
+                position,
+            } => write!(
+                f,
+                "invalid format {:?}: specifier {} at position {} is not supported",
+                format, spec, position
+            ),
+            PatternError::Regex { format, source } => {
+                write!(f, "invalid format {:?}: {}", format, source)
+            }
+        }
+    }
+}
+
+impl Error for PatternError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PatternError::Regex { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Why a config file could not be loaded.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file could not be read.
+    Io {
+        /// The config file.
+        path: PathBuf,
+        /// The read error.
+        source: io::Error,
+    },
+    /// The file is not valid TOML or has settings logzen does not know.
+    Parse {
+        /// The config file.
+        path: PathBuf,
+        /// The parse error.
+        source: toml::de::Error,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => {
+                write!(f, "cannot read config {}: {}", path.display(), source)
+            }
+            ConfigError::Parse { path, source } => {
+                write!(f, "invalid config {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            ConfigError::Parse { source, .. } => Some(source),
+        }
+    }
+}
+
+/// Why a timezone name or `ABBR=ZONE` override could not be used.
+#[derive(Debug)]
+pub enum ZoneError {
+    /// The name is not an IANA zone.
+    UnknownZone(String),
+    /// An abbreviation override is not of the form `ABBR=ZONE`.
+    InvalidOverride(String),
+}
+
+impl fmt::Display for ZoneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ZoneError::UnknownZone(name) => write!(f, "unknown timezone: {}", name),
+            ZoneError::InvalidOverride(value) => {
+                write!(f, "expected ABBR=ZONE, got: {}", value)
+            }
+        }
+    }
+}
+
+impl Error for ZoneError {}
+
+/// Why an output format is neither a preset nor a valid strftime format.
+#[derive(Debug)]
+pub struct OutputFormatError {
+    /// The output format as given.
+    pub spec: String,
+}
+
+impl fmt::Display for OutputFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid output format: {}", self.spec)
+    }
+}
+
+impl Error for OutputFormatError {}
+
+/// Why an access log layout could not be compiled into a [`crate::access::AccessFormat`].
+#[derive(Debug)]
+pub enum AccessFormatError {
+    /// A `${` has no closing `}`.
+    UnclosedBrace {
+        /// The layout, with `combined` or `common` spelled out.
+        format: String,
+    },
+    /// A `$` is not followed by a variable name.
+    MissingName {
+        /// The layout, with `combined` or `common` spelled out.
+        format: String,
+    },
+    /// The layout has no variables to read.
+    NoVariables {
+        /// The layout, with `combined` or `common` spelled out.
+        format: String,
+    },
+    /// The generated regex failed to compile.
+    Regex {
+        /// The layout, with `combined` or `common` spelled out.
+        format: String,
+        /// The regex error.
+        source: regex::Error,
+    },
+}
+
+impl fmt::Display for AccessFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccessFormatError::UnclosedBrace { format } => {
+                write!(f, "invalid log format {:?}: unclosed ${{", format)
+            }
+            AccessFormatError::MissingName { format } => {
+                write!(
+                    f,
+                    "invalid log format {:?}: $ without a variable name",
+                    format
+                )
+            }
+            AccessFormatError::NoVariables { format } => {
+                write!(f, "invalid log format {:?}: no variables", format)
+            }
+            AccessFormatError::Regex { format, source } => {
+                write!(f, "invalid log format {:?}: {}", format, source)
+            }
+        }
+    }
+}
+
+impl Error for AccessFormatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AccessFormatError::Regex { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Why a `--pretty` template could not be parsed into a [`crate::pretty::Template`].
+#[derive(Debug)]
+pub enum TemplateError {
+    /// A `{` has no closing `}`.
+    Unclosed {
+        /// The template as given.
+        template: String,
+    },
+    /// A `}` closes nothing; a literal brace is written `}}`.
+    Unmatched {
+        /// The template as given.
+        template: String,
+    },
+    /// A placeholder names an empty field, as in `{}` or `{a|}`.
+    EmptyField {
+        /// The template as given.
+        template: String,
+    },
+    /// A placeholder ends in a modifier other than `:upper` or `:lower`.
+    UnknownModifier {
+        /// The template as given.
+        template: String,
+        /// The modifier, without its `:`.
+        modifier: String,
+    },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateError::Unclosed { template } => {
+                write!(f, "invalid template {:?}: unclosed {{", template)
+            }
+            TemplateError::Unmatched { template } => {
+                write!(f, "invalid template {:?}: unmatched }}", template)
+            }
+            TemplateError::EmptyField { template } => {
+                write!(f, "invalid template {:?}: empty field name", template)
+            }
+            TemplateError::UnknownModifier { template, modifier } => write!(
+                f,
+                "invalid template {:?}: unknown modifier :{}",
+                template, modifier
+            ),
+        }
+    }
+}
+
+impl Error for TemplateError {}
+
+/// Why a `--since`/`--until` expression could not be read as a time.
+#[derive(Debug)]
+pub struct TimeExpressionError {
+    /// The expression as given.
+    pub expr: String,
+}
+
+impl fmt::Display for TimeExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unrecognised time expression: {}", self.expr)
+    }
+}
+
+impl Error for TimeExpressionError {}
@@ -0,0 +1,97 @@
+use std::io::{self, prelude::*};
+
+use logzen::{find_timestamps, Conversion, ParseOptions, PatternSet};
+
+/// What one pattern matched while scanning the sample lines.
+#[derive(Default)]
+struct Findings {
+    hits: usize,
+    converted: usize,
+    samples: Vec<String>,
+}
+
+/// Describes how each pattern in `regex_list` is compiled and what it matches in `lines`.
+///
+/// For every pattern this writes its format, its regex and whether it is naive or zulu, then
+/// how often it matched in the first `max_lines` lines and up to `samples` examples. A match is
+/// only converted if it parses and no longer or earlier pattern claimed the same text, so
+/// examples that were not converted point at formats that are shadowed or do not parse.
+pub fn explain<I, W>(
+    regex_list: &PatternSet,
+    options: &ParseOptions,
+    conversion: &Conversion,
+    lines: I,
+    max_lines: usize,
+    samples: usize,
+    out: &mut W,
+) -> io::Result<()>
+where
+    I: IntoIterator<Item = io::Result<Vec<u8>>>,
+    W: Write,
+{
+    let mut findings: Vec<Findings> = regex_list
+        .patterns()
+        .iter()
+        .map(|_| Findings::default())
+        .collect();
+    let mut scanned = 0;
+    for line in lines.into_iter().take(max_lines) {
+        let line = line?;
+        scanned += 1;
+        let found = find_timestamps(&line, regex_list, options);
+        for (pattern, findings) in regex_list.patterns().iter().zip(&mut findings) {
+            for m in pattern.regex().find_iter(&line) {
+                findings.hits += 1;
+                let text = String::from_utf8_lossy(m.as_bytes());
+                let converted = found.iter().find(|f| {
+                    f.start == m.start() && f.end == m.end() && std::ptr::eq(f.pattern, pattern)
+                });
+                let sample = match converted {
+                    Some(f) => {
+                        findings.converted += 1;
+                        let rendered = conversion.render(&f.datetime, pattern);
+                        format!("line {}: {} -> {}", scanned, text, rendered)
+                    }
+                    None => format!("line {}: {} (not converted)", scanned, text),
+                };
+                if findings.samples.len() < samples {
+                    findings.samples.push(sample);
+                }
+            }
+        }
+    }
+
+    for (idx, (pattern, findings)) in regex_list.patterns().iter().zip(&findings).enumerate() {
+        if idx > 0 {
+            writeln!(out)?;
+        }
+        writeln!(out, "{}", pattern.format())?;
+        writeln!(out, "  regex: {}", pattern.regex())?;
+        writeln!(
+            out,
+            "  naive: {}, zulu: {}",
+            yes_no(pattern.is_naive()),
+            yes_no(pattern.is_zulu())
+        )?;
+        if scanned == 0 {
+            continue;
+        }
+        writeln!(
+            out,
+            "  matches: {} in {} lines, {} converted",
+            findings.hits, scanned, findings.converted
+        )?;
+        for sample in &findings.samples {
+            writeln!(out, "    {}", sample)?;
+        }
+    }
+    Ok(())
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
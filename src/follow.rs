@@ -0,0 +1,155 @@
+use std::fs::{self, File};
+use std::io::{self, prelude::*, SeekFrom};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use crate::diag::report_skipped_line;
+use crate::lines::is_line_error;
+use crate::lines::ByteLines;
+use crate::merge::Labels;
+use logzen::window::{TimeWindow, WindowFilter};
+use logzen::{LineConverter, ParseOptions};
+
+/// How long to wait at end of file before checking for new data, truncation or rotation.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Reads a file like `tail -F`: at end of file it waits for more data instead of returning 0.
+///
+/// When the file shrinks below the read position it is treated as truncated and read again from
+/// the start. When the path starts pointing at a different file (rename-based rotation), the
+/// old file is drained first and then the new one is opened and read from the start.
+pub struct FollowReader {
+    path: PathBuf,
+    file: File,
+    position: u64,
+}
+
+impl FollowReader {
+    /// Follows `file`, which was opened from `path`, from its current position.
+    pub fn new(path: PathBuf, mut file: File) -> io::Result<FollowReader> {
+        let position = file.stream_position()?;
+        Ok(FollowReader {
+            path,
+            file,
+            position,
+        })
+    }
+
+    /// Switches to the file now at `path` if it is no longer the one being read.
+    fn reopen_if_rotated(&mut self) -> io::Result<bool> {
+        let current = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            // Mid-rotation the path may briefly not exist; keep the old file until it does.
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        if same_file(&self.file.metadata()?, &current) {
+            return Ok(false);
+        }
+        info!("{} was rotated, reopening it", self.path.display());
+        self.file = File::open(&self.path)?;
+        self.position = 0;
+        Ok(true)
+    }
+
+    fn rewind_if_truncated(&mut self) -> io::Result<bool> {
+        if self.file.metadata()?.len() >= self.position {
+            return Ok(false);
+        }
+        info!(
+            "{} was truncated, reading it from the start",
+            self.path.display()
+        );
+        self.position = self.file.seek(SeekFrom::Start(0))?;
+        Ok(true)
+    }
+}
+
+impl Read for FollowReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.file.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                self.position += read as u64;
+                return Ok(read);
+            }
+            if self.reopen_if_rotated()? || self.rewind_if_truncated()? {
+                continue;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+fn same_file(_: &fs::Metadata, _: &fs::Metadata) -> bool {
+    true
+}
+
+/// Reads every input on its own thread and writes lines in the order they arrive.
+///
+/// Used instead of [`crate::merge::merge`] when following several files, since a merge by time
+/// would stall waiting on whichever file is quiet. Each input keeps its own parse state and
+/// window filter.
+pub fn interleave<W: Write>(
+    inputs: Vec<(String, ByteLines<Box<dyn BufRead + Send>>)>,
+    converter: LineConverter,
+    options: &ParseOptions,
+    window: &TimeWindow,
+    labels: Labels,
+    out: &mut W,
+) -> io::Result<()> {
+    let names: Vec<String> = inputs.iter().map(|(name, _)| name.clone()).collect();
+    let prefixes = labels.prefixes(&names);
+    let mut states: Vec<(ParseOptions, WindowFilter, u64)> = names
+        .iter()
+        .map(|_| (options.clone(), WindowFilter::new(*window), 0))
+        .collect();
+
+    let (sender, receiver) = mpsc::channel();
+    for (idx, (_, lines)) in inputs.into_iter().enumerate() {
+        let sender = sender.clone();
+        thread::spawn(move || {
+            for line in lines {
+                let failed = line.as_ref().is_err_and(|e| !is_line_error(e));
+                if sender.send((idx, line)).is_err() || failed {
+                    break;
+                }
+            }
+        });
+    }
+    drop(sender);
+
+    for (idx, line) in receiver {
+        let (options, filter, line_number) = &mut states[idx];
+        *line_number += 1;
+        let line = match line {
+            Ok(line) => line,
+            Err(e) if is_line_error(&e) => {
+                report_skipped_line(&names[idx], *line_number, &e);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let (time, converted) = match converter.convert(&line, options) {
+            Some(converted) => converted,
+            None => continue,
+        };
+        if !filter.keep(time) {
+            continue;
+        }
+        out.write_all(prefixes[idx].as_bytes())?;
+        out.write_all(&converted)?;
+        out.write_all(b"\n")?;
+        out.flush()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,146 @@
+//! Timestamp fields of structured records such as JSON and logfmt lines.
+
+use chrono::{DateTime, FixedOffset};
+use serde_json::{Map, Number, Value};
+
+use crate::parse::parse_epoch;
+use crate::{
+    convert_dt_spec_regex, find_timestamps, parse_timestamp, replace_timestamps, Conversion,
+    DateTimePattern, ParseOptions, PatternSet,
+};
+
+/// Keys looked up when no others are given.
+pub const DEFAULT_TIME_KEYS: &[&str] = &["time", "ts", "timestamp", "@timestamp"];
+
+/// The key of a record's first timestamp field and the time it holds.
+pub type RecordTime<'k> = (&'k str, DateTime<FixedOffset>);
+
+/// The fields of a structured record, such as a JSON or logfmt line, that hold its timestamps.
+#[derive(Debug)]
+pub struct TimeFields {
+    keys: Vec<String>,
+    /// Renders epoch numbers the way a matched `%s` is rendered.
+    epoch: DateTimePattern,
+}
+
+impl TimeFields {
+    /// Looks timestamps up under `keys`, in order.
+    ///
+    /// A key can be a path into nested objects such as `meta.time`. A key that itself contains
+    /// dots, as in `{"event.created": ...}`, is matched before it is split into a path.
+    pub fn new<S: AsRef<str>>(keys: &[S]) -> TimeFields {
+        TimeFields {
+            keys: keys.iter().map(|key| key.as_ref().to_string()).collect(),
+            epoch: convert_dt_spec_regex("%s").expect("%s compiles"),
+        }
+    }
+
+    /// Converts the timestamp fields of `record` in place.
+    ///
+    /// Returns the key of the first field that holds a timestamp with its time, and whether
+    /// any field changed.
+    pub fn convert_fields(
+        &self,
+        record: &mut Map<String, Value>,
+        regex_list: &PatternSet,
+        options: &ParseOptions,
+        conversion: &Conversion,
+    ) -> (Option<RecordTime<'_>>, bool) {
+        let mut time = None;
+        let mut changed = false;
+        let mut converted: Vec<*const Value> = Vec::new();
+        for key in &self.keys {
+            let field = match lookup_mut(record, key) {
+                Some(field) => field,
+                None => continue,
+            };
+            // Two keys can lead to the same field; convert it only once.
+            if converted.contains(&(field as *const Value)) {
+                continue;
+            }
+            if let Some((dt, value)) = self.convert_value(field, regex_list, options, conversion) {
+                time.get_or_insert((key.as_str(), dt));
+                if *field != value {
+                    *field = value;
+                    changed = true;
+                }
+                converted.push(field as *const Value);
+            }
+        }
+        (time, changed)
+    }
+
+    fn convert_value(
+        &self,
+        value: &Value,
+        regex_list: &PatternSet,
+        options: &ParseOptions,
+        conversion: &Conversion,
+    ) -> Option<(DateTime<FixedOffset>, Value)> {
+        match value {
+            Value::Number(number) => {
+                let dt = parse_epoch(&number.to_string())?;
+                let rendered = conversion.render(&dt, &self.epoch);
+                // Epoch output stays a number.
+                let value = match rendered.parse::<Number>() {
+                    Ok(number) => Value::Number(number),
+                    Err(_) => Value::String(rendered),
+                };
+                Some((dt, value))
+            }
+            Value::String(text) => {
+                // A field that is just a timestamp is taken whole, so epochs in strings are
+                // accepted without the key context they need elsewhere.
+                for idx in regex_list.matching(text.as_bytes()) {
+                    let pattern = &regex_list.patterns()[idx];
+                    let whole = pattern
+                        .regex()
+                        .find(text.as_bytes())
+                        .is_some_and(|m| m.start() == 0 && m.end() == text.len());
+                    if !whole {
+                        continue;
+                    }
+                    if let Some(dt) = parse_timestamp(text, pattern, options) {
+                        return Some((dt, Value::String(conversion.render_field(&dt, pattern))));
+                    }
+                }
+                // Epochs are often written as strings, and logfmt values are always strings.
+                if let Some(dt) = parse_epoch(text) {
+                    return Some((dt, Value::String(conversion.render(&dt, &self.epoch))));
+                }
+                let found = find_timestamps(text.as_bytes(), regex_list, options);
+                let dt = found.first()?.datetime;
+                let replaced = replace_timestamps(text.as_bytes(), &found, conversion);
+                Some((dt, Value::String(String::from_utf8(replaced).ok()?)))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Finds the value under `key`, either as a key of `object` or as a dotted path into it.
+pub(crate) fn lookup<'v>(object: &'v Map<String, Value>, key: &str) -> Option<&'v Value> {
+    if let Some(value) = object.get(key) {
+        return Some(value);
+    }
+    key.match_indices('.')
+        .find_map(|(idx, _)| match object.get(&key[..idx]) {
+            Some(Value::Object(inner)) => lookup(inner, &key[idx + 1..]),
+            _ => None,
+        })
+}
+
+/// Like [`lookup`], for changing the value.
+fn lookup_mut<'v>(object: &'v mut Map<String, Value>, key: &str) -> Option<&'v mut Value> {
+    if object.contains_key(key) {
+        return object.get_mut(key);
+    }
+    let split = key
+        .match_indices('.')
+        .map(|(idx, _)| idx)
+        .find(|&idx| matches!(object.get(&key[..idx]), Some(Value::Object(_))))?;
+    match object.get_mut(&key[..split]) {
+        Some(Value::Object(inner)) => lookup_mut(inner, &key[split + 1..]),
+        _ => None,
+    }
+}
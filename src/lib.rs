@@ -0,0 +1,59 @@
+//! Timestamp detection and conversion for log files.
+//!
+//! Formats are strftime strings compiled into [`DateTimePattern`]s by
+//! [`convert_dt_spec_regex`] and searched together as a [`PatternSet`]. [`find_timestamps`]
+//! locates every timestamp in a line, returning its byte span and parsed value, and
+//! [`replace_timestamps`] splices converted timestamps back in according to a [`Conversion`].
+//! Lines are handled as bytes, so input that is not valid UTF-8 passes through unchanged apart
+//! from its timestamps.
+//!
+//! ```
+//! use logzen::{
+//!     convert_dt_spec_regex, find_and_replace_timestamp, Conversion, OutputFormat,
+//!     ParseOptions, PatternSet, TargetZone,
+//! };
+//!
+//! let patterns = PatternSet::new(vec![convert_dt_spec_regex("%Y-%m-%dT%H:%M:%SZ").unwrap()]);
+//! let conversion = Conversion {
+//!     zone: TargetZone::from_name("Asia/Kolkata").unwrap(),
+//!     output: OutputFormat::Input,
+//! };
+//! let line = find_and_replace_timestamp(
+//!     b"start=2026-10-15T10:00:00Z",
+//!     &patterns,
+//!     &ParseOptions::default(),
+//!     &conversion,
+//! );
+//! assert_eq!(line, b"start=2026-10-15T15:30:00+05:30");
+//! ```
+
+#![warn(missing_docs)]
+
+pub mod access;
+pub mod config;
+mod convert;
+pub mod decompress;
+pub mod detect;
+mod error;
+pub mod json;
+pub mod logfmt;
+mod parse;
+mod pattern;
+pub mod pretty;
+pub mod syslog;
+pub mod window;
+mod zone;
+
+pub use convert::{
+    find_and_replace_timestamp, replace_timestamps, Conversion, LineConverter, LineFormat,
+    OutputFormat, ParsedRecord, RecordOutput, ISO_FORMAT,
+};
+pub use error::{
+    AccessFormatError, ConfigError, OutputFormatError, PatternError, TemplateError,
+    TimeExpressionError, ZoneError,
+};
+pub use parse::{
+    find_timestamps, parse_timestamp, EpochOptions, ParseOptions, TimestampMatch, YearInference,
+};
+pub use pattern::{convert_dt_spec_regex, DateTimePattern, PatternSet, DEFAULT_FORMATS};
+pub use zone::{parse_abbreviation_override, TargetZone, ZoneAbbreviations, ZONE_ABBREVIATIONS};
@@ -0,0 +1,65 @@
+use std::borrow::Cow;
+use std::io::{self, prelude::*};
+
+/// What happens to bytes that are not valid UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidUtf8 {
+    /// Write them out exactly as they were read.
+    Passthrough,
+    /// Replace each invalid sequence with U+FFFD before the line is processed.
+    Lossy,
+}
+
+impl InvalidUtf8 {
+    /// Applies this policy to `line`, only copying it if it has to change.
+    pub fn apply(self, line: &[u8]) -> Cow<'_, [u8]> {
+        match self {
+            InvalidUtf8::Lossy if std::str::from_utf8(line).is_err() => {
+                Cow::Owned(String::from_utf8_lossy(line).into_owned().into_bytes())
+            }
+            _ => Cow::Borrowed(line),
+        }
+    }
+}
+
+/// Whether a read error only affects the line being read, so the rest of the input can still
+/// be processed.
+pub fn is_line_error(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::InvalidData
+}
+
+/// Iterates over the lines of a reader as raw bytes, so binary payloads and legacy encodings
+/// do not stop the input.
+///
+/// Lines are returned without their trailing `\n`; a `\r` before it is kept so CRLF input is
+/// written back unchanged.
+pub struct ByteLines<R> {
+    reader: R,
+    invalid: InvalidUtf8,
+}
+
+impl<R: BufRead> ByteLines<R> {
+    pub fn new(reader: R, invalid: InvalidUtf8) -> ByteLines<R> {
+        ByteLines { reader, invalid }
+    }
+}
+
+impl<R: BufRead> Iterator for ByteLines<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<io::Result<Vec<u8>>> {
+        let mut line = Vec::new();
+        match self.reader.read_until(b'\n', &mut line) {
+            Ok(0) => return None,
+            Ok(_) => {}
+            Err(e) => return Some(Err(e)),
+        }
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+        if let Cow::Owned(fixed) = self.invalid.apply(&line) {
+            line = fixed;
+        }
+        Some(Ok(line))
+    }
+}
@@ -0,0 +1,259 @@
+//! Reading and writing logfmt lines such as `ts=2026-10-15T10:00:00Z level=info msg="hi"`.
+
+use serde_json::{Map, Value};
+use std::ops::Range;
+
+/// One `key=value` pair of a logfmt line.
+#[derive(Debug, Clone)]
+pub struct Pair {
+    /// The key, which may contain dots but no spaces, quotes or `=`.
+    pub key: String,
+    /// The unquoted value, or `Null` for a key without `=`.
+    pub value: Value,
+    /// Where the value is written in the line, including its quotes.
+    pub span: Range<usize>,
+}
+
+/// Splits a logfmt line such as `ts=2026-10-15T10:00:00Z level=info msg="disk \"a\" full"`
+/// into its pairs.
+///
+/// Quoted values use the escapes of JSON strings. Returns `None` if the line is not logfmt,
+/// either because it has no `key=value` pair or because a value is badly quoted.
+pub fn parse(line: &[u8]) -> Option<Vec<Pair>> {
+    let text = std::str::from_utf8(line).ok()?;
+    let bytes = text.as_bytes();
+    let mut pairs = Vec::new();
+    let mut has_value = false;
+    let mut pos = 0;
+    loop {
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if pos == bytes.len() {
+            break;
+        }
+        let start = pos;
+        while pos < bytes.len()
+            && !matches!(bytes[pos], b'=' | b'"')
+            && !bytes[pos].is_ascii_whitespace()
+        {
+            pos += 1;
+        }
+        if pos == start || bytes.get(pos) == Some(&b'"') {
+            return None;
+        }
+        let key = text[start..pos].to_string();
+        if bytes.get(pos) != Some(&b'=') {
+            pairs.push(Pair {
+                key,
+                value: Value::Null,
+                span: pos..pos,
+            });
+            continue;
+        }
+        pos += 1;
+        has_value = true;
+        let value_start = pos;
+        let value = if bytes.get(pos) == Some(&b'"') {
+            pos += 1;
+            loop {
+                match bytes.get(pos)? {
+                    b'\\' => pos += 2,
+                    b'"' => break,
+                    _ => pos += 1,
+                }
+            }
+            pos += 1;
+            serde_json::from_str::<String>(&text[value_start..pos]).ok()?
+        } else {
+            while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            text[value_start..pos].to_string()
+        };
+        pairs.push(Pair {
+            key,
+            value: Value::String(value),
+            span: value_start..pos,
+        });
+    }
+    if has_value {
+        Some(pairs)
+    } else {
+        None
+    }
+}
+
+/// Collects `pairs` into a record; a repeated key keeps its last value.
+pub fn to_record(pairs: &[Pair]) -> Map<String, Value> {
+    pairs
+        .iter()
+        .map(|pair| (pair.key.clone(), pair.value.clone()))
+        .collect()
+}
+
+/// Writes `record` back into `line`, replacing only the values that changed so the rest of
+/// the line keeps its spacing and quoting.
+pub fn splice(line: &[u8], pairs: &[Pair], record: &Map<String, Value>) -> Vec<u8> {
+    let mut out = String::with_capacity(line.len());
+    let mut cursor = 0;
+    // `parse` only accepts UTF-8.
+    let text = String::from_utf8_lossy(line);
+    for (idx, pair) in pairs.iter().enumerate() {
+        // A repeated key only keeps its last value in the record.
+        if pairs[idx + 1..].iter().any(|later| later.key == pair.key) {
+            continue;
+        }
+        let value = match record.get(&pair.key) {
+            Some(value) if *value != pair.value => value,
+            _ => continue,
+        };
+        out.push_str(&text[cursor..pair.span.start]);
+        if pair.span.is_empty() {
+            out.push('=');
+        }
+        push_value(&mut out, value);
+        cursor = pair.span.end;
+    }
+    out.push_str(&text[cursor..]);
+    out.into_bytes()
+}
+
+/// Renders `record` as a logfmt line, flattening nested objects into dotted keys.
+pub fn write(record: &Map<String, Value>) -> Vec<u8> {
+    let mut out = String::new();
+    push_pairs(&mut out, record, "", &[], true);
+    match out.strip_prefix(' ') {
+        Some(rest) => rest.as_bytes().to_vec(),
+        None => out.into_bytes(),
+    }
+}
+
+/// Appends ` key=value` for every field of `record` not in `skip`, flattening nested objects
+/// into dotted keys. Null fields are written as a bare key, or left out unless `nulls`.
+pub(crate) fn push_pairs(
+    out: &mut String,
+    record: &Map<String, Value>,
+    prefix: &str,
+    skip: &[&str],
+    nulls: bool,
+) {
+    for (key, value) in record {
+        let path = format!("{}{}", prefix, key);
+        if skip.contains(&path.as_str()) {
+            continue;
+        }
+        match value {
+            Value::Object(inner) if !inner.is_empty() => {
+                push_pairs(out, inner, &format!("{}.", path), skip, nulls)
+            }
+            Value::Null if !nulls => {}
+            // A key without a value reads back as null.
+            Value::Null => {
+                out.push(' ');
+                out.push_str(&path);
+            }
+            value => {
+                out.push(' ');
+                out.push_str(&path);
+                out.push('=');
+                push_value(out, value);
+            }
+        }
+    }
+}
+
+/// Appends `value`, quoted if it would not read back as a single value.
+fn push_value(out: &mut String, value: &Value) {
+    let text = match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    };
+    let quote = text.is_empty()
+        || text
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || c == '"' || c == '=');
+    if quote {
+        out.push_str(&Value::String(text).to_string());
+    } else {
+        out.push_str(&text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(line: &str) -> Vec<(String, Value)> {
+        parse(line.as_bytes())
+            .unwrap()
+            .into_iter()
+            .map(|pair| (pair.key, pair.value))
+            .collect()
+    }
+
+    #[test]
+    fn reads_quoted_values_with_json_escapes() {
+        assert_eq!(
+            values(r#"level=info msg="disk \"a\" full\n" path="C:\\logs""#),
+            vec![
+                ("level".to_string(), Value::from("info")),
+                ("msg".to_string(), Value::from("disk \"a\" full\n")),
+                ("path".to_string(), Value::from("C:\\logs")),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_bare_keys_and_values_containing_equals() {
+        assert_eq!(
+            values("debug query=a=b empty="),
+            vec![
+                ("debug".to_string(), Value::Null),
+                ("query".to_string(), Value::from("a=b")),
+                ("empty".to_string(), Value::from("")),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_lines_that_are_not_logfmt() {
+        assert!(parse(b"just some words").is_none());
+        assert!(parse(br#"msg="unterminated"#).is_none());
+        assert!(parse(br#"msg="escape at the end\"#).is_none());
+        assert!(parse(br#"msg="bad \q escape""#).is_none());
+        assert!(parse(br#""key"=value"#).is_none());
+    }
+
+    #[test]
+    fn splices_only_changed_values() {
+        let line = br#"ts=1  level=info   msg="hi there" ts=2"#;
+        let pairs = parse(line).unwrap();
+        let mut record = to_record(&pairs);
+        assert_eq!(record["ts"], "2");
+        record.insert("ts".to_string(), Value::from("2026-10-15 10:00:00"));
+        record.insert("level".to_string(), Value::from("a=b"));
+        assert_eq!(
+            String::from_utf8(splice(line, &pairs, &record)).unwrap(),
+            r#"ts=1  level="a=b"   msg="hi there" ts="2026-10-15 10:00:00""#
+        );
+    }
+
+    #[test]
+    fn splices_a_value_into_a_bare_key() {
+        let line = b"debug msg=hi";
+        let pairs = parse(line).unwrap();
+        let mut record = to_record(&pairs);
+        record.insert("debug".to_string(), Value::from(true));
+        assert_eq!(splice(line, &pairs, &record), b"debug=true msg=hi");
+    }
+
+    #[test]
+    fn writes_nested_records_with_dotted_keys() {
+        let record = serde_json::json!({"a": {"b": 1, "c": null}, "msg": "two words", "e": ""});
+        assert_eq!(
+            write(record.as_object().unwrap()),
+            br#"a.b=1 a.c msg="two words" e="""#
+        );
+    }
+}
@@ -1,211 +1,923 @@
-use chrono::format::{Item, Pad, StrftimeItems};
-use chrono::Utc;
-use clap::{App, Arg};
-
-use regex::Regex;
-use std::collections::HashSet;
-use std::fmt::Write;
+use clap::{App, Arg, SubCommand};
 use std::fs::File;
-use std::io::{self, prelude::*, BufReader};
+use std::io::{self, prelude::*, BufReader, BufWriter, IsTerminal, SeekFrom};
+use std::thread;
 
-const VERSION: &str = env!("CARGO_PKG_VERSION");
+use chrono::Utc;
+use logzen::access::AccessFormat;
+use logzen::config::Config;
+use logzen::json::{TimeFields, DEFAULT_TIME_KEYS};
+use logzen::pretty::{Pretty, Template, DEFAULT_TEMPLATE};
+use logzen::syslog::{severity_level, SyslogFormat};
+use logzen::window::{self, TimeWindow, WindowFilter};
+use logzen::{
+    convert_dt_spec_regex, parse_abbreviation_override, Conversion, LineConverter, LineFormat,
+    OutputFormat, ParseOptions, PatternSet, RecordOutput, TargetZone,
+};
+use logzen::{decompress, detect};
 
-const LONG_MONTHS: &'static str =
-    "January|February|March|April|May|June|July|August|September|October|November|December";
+#[macro_use]
+mod diag;
+mod explain;
+mod follow;
+mod lines;
+mod merge;
+mod mmap;
+mod parallel;
+mod seek;
 
-const SHORT_WEEKDAYS: &'static str = "Mon|Tue|Wed|Thu|Fri|Sat|Sun";
-const LONG_WEEKDAYS: &'static str = "Monday|Tuesday|Wednesday|Thursday|Friday|Saturday|Sunday";
-const LOWER_AM_PM: &'static str = "am|pm";
-const UPPER_AM_PM: &'static str = "AM|PM";
-const SHORT_MONTHS: &'static str = "Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Oct|Nov|Dec";
+use diag::{report_skipped_line, Verbosity};
+use lines::{is_line_error, ByteLines, InvalidUtf8};
+use merge::{Labels, RecordReader, Source};
+use mmap::MappedFile;
+use parallel::Parallel;
 
-const TWO_DIGITS: &'static str = r"\d{2}";
-const FOUR_DIGITS: &'static str = r"\d{4}";
-const THREE_DIGITS: &'static str = r"\d{3}";
-const SIX_DIGITS: &'static str = r"\d{6}";
-const NINE_DIGITS: &'static str = r"\d{9}";
-const NANO_SECOND_REGEX: &'static str = r"(?:\d{9}|\d{6}|\d{3})";
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Output is written in blocks of this size unless it has to appear line by line.
+const OUTPUT_BUFFER: usize = 64 * 1024;
 
-const DEFAULT_FORMATS: [&str; 4] = ["%+", "%c", "%Y-%m-%dT%H:%M:%SZ", "%Y-%m-%dT%H:%M:%S%z"];
+fn main() {
+    if let Err(e) = run() {
+        // The reader went away (e.g. `logzen ... | head`); that is not worth reporting.
+        if e.downcast_ref::<io::Error>()
+            .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+        {
+            return;
+        }
+        eprintln!("logzen: {}", e);
+        std::process::exit(1);
+    }
+}
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn run() -> Result<(), Box<dyn std::error::Error>> {
     let matches = App::new("logzen")
         .version(VERSION)
         .about("CLI Log Utilities")
-        .arg(Arg::with_name("input").help("Log File"))
+        .arg(
+            Arg::with_name("input")
+                .multiple(true)
+                .help("Log files or globs, merged by time; - reads stdin"),
+        )
         .arg(
             Arg::with_name("format")
+                .global(true)
                 .short("f")
                 .long("format")
                 .multiple(true)
                 .takes_value(true)
-                .number_of_values(1),
+                .number_of_values(1)
+                .value_name("FORMAT")
+                .help(
+                    "strftime format of the timestamps, only matched at the start of a line if \
+                     it begins with ^; detected from the input if not given",
+                ),
+        )
+        .arg(
+            Arg::with_name("no-default-formats")
+                .long("no-default-formats")
+                .global(true)
+                .help("Only try the given, configured or detected formats"),
+        )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .global(true)
+                .env("LOGZEN_CONFIG")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Config file; defaults to ~/.config/logzen/config.toml if it exists"),
+        )
+        .arg(
+            Arg::with_name("tz")
+                .global(true)
+                .long("tz")
+                .env("LOGZEN_TZ")
+                .takes_value(true)
+                .value_name("ZONE")
+                .validator(|z| TargetZone::from_name(&z).map(|_| ()).map_err(|e| e.to_string()))
+                .help("IANA timezone to convert timestamps into (e.g. UTC, Asia/Kolkata)"),
+        )
+        .arg(
+            Arg::with_name("output-format")
+                .global(true)
+                .long("output-format")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .validator(|f| OutputFormat::from_spec(&f).map(|_| ()).map_err(|e| e.to_string()))
+                .help(
+                    "strftime format for rewritten timestamps, or one of the presets \
+                     iso, rfc2822, human, epoch, epoch-ms",
+                ),
+        )
+        .arg(
+            Arg::with_name("tz-abbrev")
+                .global(true)
+                .long("tz-abbrev")
+                .multiple(true)
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("ABBR=ZONE")
+                .validator(|o| parse_abbreviation_override(&o).map(|_| ()).map_err(|e| e.to_string()))
+                .help("Zone a %Z abbreviation resolves to, e.g. IST=Europe/Dublin"),
+        )
+        .arg(
+            Arg::with_name("year")
+                .global(true)
+                .long("year")
+                .takes_value(true)
+                .value_name("YEAR")
+                .validator(|y| y.parse::<i32>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Year to assume for timestamps without one, such as syslog's"),
+        )
+        .arg(
+            Arg::with_name("epoch-key")
+                .global(true)
+                .long("epoch-key")
+                .multiple(true)
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("KEY")
+                .help(
+                    "Only treat epoch numbers as timestamps when they are the value of KEY \
+                     [default: time, ts, timestamp, @timestamp]",
+                ),
+        )
+        .arg(
+            Arg::with_name("epoch-any-key")
+                .global(true)
+                .long("epoch-any-key")
+                .conflicts_with("epoch-key")
+                .help("Treat epoch numbers as timestamps when they are the value of any key"),
+        )
+        .arg(
+            Arg::with_name("epoch-anywhere")
+                .global(true)
+                .long("epoch-anywhere")
+                .help("Also treat bare epoch numbers that are not a key's value as timestamps"),
+        )
+        .arg(
+            Arg::with_name("since")
+                .long("since")
+                .takes_value(true)
+                .value_name("TIME")
+                .allow_hyphen_values(true)
+                .help(
+                    "Drop lines before TIME: a timestamp, YYYY-MM-DD[ HH:MM[:SS]], HH:MM, \
+                     now, -2h, yesterday 09:00, ...",
+                ),
+        )
+        .arg(
+            Arg::with_name("until")
+                .long("until")
+                .takes_value(true)
+                .value_name("TIME")
+                .allow_hyphen_values(true)
+                .help("Drop lines after TIME; accepts the same forms as --since"),
+        )
+        .arg(
+            Arg::with_name("bisect")
+                .long("bisect")
+                .requires("input")
+                .help(
+                    "Input is sorted by time: binary-search the file for --since and stop \
+                     after --until",
+                ),
+        )
+        .arg(
+            Arg::with_name("follow")
+                .short("F")
+                .long("follow")
+                .requires("input")
+                .help(
+                    "Keep reading files as they grow, reopening them on truncation or \
+                     rotation, like tail -F",
+                ),
+        )
+        .arg(
+            Arg::with_name("mmap")
+                .long("mmap")
+                .requires("input")
+                .conflicts_with("follow")
+                .help(
+                    "Map input files into memory instead of reading them. Faster on large \
+                     files, but logzen is killed if a file is truncated while it is read, as \
+                     logrotate's copytruncate does to live logs",
+                ),
+        )
+        .arg(
+            Arg::with_name("jobs")
+                .short("j")
+                .long("jobs")
+                .takes_value(true)
+                .value_name("N")
+                .default_value("1")
+                .validator(|n| n.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Convert a single input on N threads; 0 uses one per CPU"),
+        )
+        .arg(
+            Arg::with_name("label")
+                .long("label")
+                .help("Prefix every line with the file it came from"),
+        )
+        .arg(
+            Arg::with_name("detect")
+                .long("detect")
+                .help("Print the timestamp formats detected in the input and exit"),
+        )
+        .arg(
+            Arg::with_name("detect-lines")
+                .long("detect-lines")
+                .global(true)
+                .takes_value(true)
+                .value_name("N")
+                .default_value("200")
+                .validator(|n| n.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help(
+                    "Lines sampled from each input to detect formats when none are given; \
+                     output from a pipe starts once this many lines have arrived",
+                ),
+        )
+        .arg(
+            Arg::with_name("lossy")
+                .long("lossy")
+                .global(true)
+                .help("Replace bytes that are not valid UTF-8 with U+FFFD instead of copying them"),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Read lines as JSON records and convert their timestamp fields"),
+        )
+        .arg(
+            Arg::with_name("logfmt")
+                .long("logfmt")
+                .conflicts_with("json")
+                .help("Read lines as logfmt records and convert their timestamp fields"),
+        )
+        .arg(
+            Arg::with_name("access")
+                .long("access")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .conflicts_with_all(&["json", "logfmt"])
+                .help("Read lines as access logs: combined, common or an nginx log_format string"),
+        )
+        .arg(
+            Arg::with_name("syslog")
+                .long("syslog")
+                .conflicts_with_all(&["json", "logfmt", "access"])
+                .help("Read lines as RFC 5424 or RFC 3164 syslog messages"),
+        )
+        .arg(
+            Arg::with_name("severity")
+                .long("severity")
+                .takes_value(true)
+                .value_name("LEVEL")
+                .requires("syslog")
+                .help(
+                    "Only show syslog messages at least as severe as LEVEL, such as warning or 4; \
+                     lines that are not syslog messages are left out",
+                ),
+        )
+        .arg(
+            Arg::with_name("app")
+                .long("app")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("APP")
+                .requires("syslog")
+                .help(
+                    "Only show syslog messages from APP; lines that are not syslog messages are \
+                     left out",
+                ),
+        )
+        .arg(
+            Arg::with_name("time-key")
+                .long("time-key")
+                .alias("json-key")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("KEY")
+                .help("Record field holding a timestamp, such as meta.time [default: time, ts, timestamp, @timestamp]"),
+        )
+        .arg(
+            Arg::with_name("to")
+                .long("to")
+                .takes_value(true)
+                .possible_values(&["json", "logfmt", "pretty"])
+                .help("Write records in another format instead of the one they were read in"),
+        )
+        .arg(
+            Arg::with_name("pretty")
+                .long("pretty")
+                .conflicts_with("to")
+                .help("Render records as readable text; short for --to pretty"),
+        )
+        .arg(
+            Arg::with_name("template")
+                .long("template")
+                .takes_value(true)
+                .value_name("TEMPLATE")
+                .help("Layout for --pretty [default: {time} {level|lvl|severity:upper} {msg|message}]"),
+        )
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .takes_value(true)
+                .possible_values(&["auto", "always", "never"])
+                .default_value("auto")
+                .help("Colour the --label prefixes and dim the extra fields of --pretty"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .multiple(true)
+                .global(true)
+                .help("Report how inputs are read on stderr; -vv also shows compiled regexes"),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .global(true)
+                .conflicts_with("verbose")
+                .help("Only report errors that stop logzen"),
+        )
+        .subcommand(
+            SubCommand::with_name("explain")
+                .about("Show how each format is compiled and what it matches in the input")
+                .arg(
+                    Arg::with_name("input")
+                        .multiple(true)
+                        .help("Log files or globs to take sample matches from; - reads stdin"),
+                )
+                .arg(
+                    Arg::with_name("lines")
+                        .long("lines")
+                        .takes_value(true)
+                        .value_name("N")
+                        .default_value("1000")
+                        .validator(|n| n.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                        .help("Number of input lines to scan"),
+                )
+                .arg(
+                    Arg::with_name("samples")
+                        .long("samples")
+                        .takes_value(true)
+                        .value_name("N")
+                        .default_value("3")
+                        .validator(|n| n.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                        .help("Number of example matches to show per format"),
+                ),
         )
         .get_matches();
+    // Global options can be given before or after the subcommand name.
+    let explain = matches.subcommand_matches("explain");
+    let args = explain.unwrap_or(&matches);
+    diag::set_verbosity(if args.is_present("quiet") {
+        Verbosity::Quiet
+    } else {
+        match args.occurrences_of("verbose") {
+            0 => Verbosity::Normal,
+            1 => Verbosity::Verbose,
+            _ => Verbosity::Debug,
+        }
+    });
+
+    let mut inputs = Vec::new();
+    for input in args.values_of("input").unwrap_or_default() {
+        inputs.extend(expand_input(input)?);
+    }
+    if inputs.is_empty() {
+        inputs.push("-".to_string());
+    }
+    let mut stdin = Stdin::default();
 
-    let mut formats: HashSet<&str> = matches
-        .values_of("format")
-        .unwrap_or(clap::Values::default())
+    let zone = match args.value_of("tz") {
+        Some(name) => TargetZone::from_name(name)?,
+        None => TargetZone::Local,
+    };
+    let output = match args.value_of("output-format") {
+        Some(spec) => OutputFormat::from_spec(spec)?,
+        None => OutputFormat::Input,
+    };
+    let conversion = Conversion { zone, output };
+    let mut options = ParseOptions::default();
+    for value in args.values_of("tz-abbrev").unwrap_or_default() {
+        let (abbr, tz) = parse_abbreviation_override(value)?;
+        options.abbreviations.overrides.insert(abbr, tz);
+    }
+    options.epochs.keys = args
+        .values_of("epoch-key")
+        .unwrap_or_default()
+        .map(String::from)
         .collect();
-    formats.extend(DEFAULT_FORMATS.iter());
-    let reader: Box<dyn BufRead> = if let Some(input_file) = matches.value_of("input") {
-        let file = File::open(input_file)?;
-        Box::new(BufReader::new(file))
+    options.epochs.any_key = args.is_present("epoch-any-key");
+    options.epochs.anywhere = args.is_present("epoch-anywhere");
+    if let Some(year) = args.value_of("year") {
+        options.years.start = Some(year.parse()?);
+    }
+
+    let config = match args.value_of("config") {
+        Some(path) => Config::load(path.as_ref())?,
+        None => match Config::default_path() {
+            Some(path) if path.exists() => Config::load(&path)?,
+            _ => Config::default(),
+        },
+    };
+    let mut formats: Vec<&str> = args.values_of("format").unwrap_or_default().collect();
+    if (formats.is_empty() && config.formats.is_empty()) || matches.is_present("detect") {
+        let max_lines = args.value_of("detect-lines").unwrap_or_default().parse()?;
+        let mut lines = Vec::new();
+        for input in &inputs {
+            let reader = if input != "-" {
+                open_plain(input)?
+            } else if !io::stdin().is_terminal() {
+                if !stdin_is_file() {
+                    // Output starts once the sample is read and goes out at once, so a slow
+                    // pipe is held back by at most --detect-lines lines.
+                    info!(
+                        "sampling up to {} lines of stdin to detect formats",
+                        max_lines
+                    );
+                }
+                stdin.take()?
+            } else {
+                // A terminal would wait for someone to type a whole sample.
+                warn!("not detecting formats on a terminal, using the defaults");
+                continue;
+            };
+            let (sample, reader) = detect::sample(reader, max_lines)?;
+            if input == "-" {
+                stdin.put_back(reader);
+            }
+            lines.extend(sample);
+        }
+        let detected = detect::detect(&lines, &options);
+        if matches.is_present("detect") {
+            if detected.is_empty() {
+                return Err(format!("no known timestamp format in {} lines", lines.len()).into());
+            }
+            for d in &detected {
+                println!(
+                    "-f '{}'  # {}, {} of {} lines",
+                    d.format,
+                    d.name,
+                    d.lines,
+                    lines.len()
+                );
+            }
+            return Ok(());
+        }
+        for d in detected {
+            info!(
+                "detected {} ({}) in {} of {} lines",
+                d.format,
+                d.name,
+                d.lines,
+                lines.len()
+            );
+            formats.push(d.format);
+        }
+    }
+    let default_formats =
+        !args.is_present("no-default-formats") && config.default_formats.unwrap_or(true);
+    let formats = config.prioritize(&formats, default_formats);
+    if formats.is_empty() {
+        return Err("no timestamp formats to try".into());
+    }
+    let regex_list = PatternSet::new(
+        formats
+            .iter()
+            .map(|f| convert_dt_spec_regex(f))
+            .collect::<Result<_, _>>()?,
+    );
+    for pattern in regex_list.patterns() {
+        debug!("{} compiles to {}", pattern.format(), pattern.regex());
+    }
+    let now = Utc::now();
+    let mut window = TimeWindow::default();
+    if let Some(expr) = matches.value_of("since") {
+        window.since = Some(window::parse_time_expr(
+            expr,
+            now,
+            &zone,
+            &regex_list,
+            &options,
+        )?);
+    }
+    if let Some(expr) = matches.value_of("until") {
+        window.until = Some(window::parse_time_expr(
+            expr,
+            now,
+            &zone,
+            &regex_list,
+            &options,
+        )?);
+    }
+    let bisect = matches.is_present("bisect");
+    let follow = matches.is_present("follow");
+    let reading = Reading {
+        bisect,
+        follow,
+        mmap: matches.is_present("mmap"),
+    };
+    let invalid = if args.is_present("lossy") {
+        InvalidUtf8::Lossy
     } else {
-        Box::new(BufReader::new(io::stdin()))
+        InvalidUtf8::Passthrough
     };
-    let regex_list: Vec<_> = formats
+
+    if let Some(explain) = explain {
+        let max_lines = explain.value_of("lines").unwrap_or_default().parse()?;
+        let samples = explain.value_of("samples").unwrap_or_default().parse()?;
+        let mut sources = Vec::new();
+        // Without input on a terminal there is nothing to sample; just describe the formats.
+        if explain.is_present("input") || !io::stdin().is_terminal() {
+            for input in &inputs {
+                let (reader, _) = open_input(
+                    input,
+                    &mut stdin,
+                    Reading::default(),
+                    &window,
+                    &regex_list,
+                    &options,
+                )?;
+                sources.push(ByteLines::new(reader.into_reader(), invalid));
+            }
+        }
+        let stdout = io::stdout();
+        explain::explain(
+            &regex_list,
+            &options,
+            &conversion,
+            sources.into_iter().flatten(),
+            max_lines,
+            samples,
+            &mut stdout.lock(),
+        )?;
+        return Ok(());
+    }
+
+    let structured = ["json", "time-key", "to", "pretty"]
         .iter()
-        .map(|f| convert_dt_spec_regex(f).unwrap())
-        .collect();
-    for line in reader.lines() {
-        let line = line.unwrap();
-        println!(
-            "{}",
-            find_and_replace_timestamp(line.as_str(), regex_list.as_slice())
-        )
+        .any(|name| matches.is_present(name));
+    let fields = match matches.values_of("time-key") {
+        Some(keys) => TimeFields::new(&keys.collect::<Vec<_>>()),
+        None => TimeFields::new(DEFAULT_TIME_KEYS),
+    };
+    let line_format = if let Some(spec) = matches.value_of("access") {
+        LineFormat::Access(AccessFormat::new(spec)?)
+    } else if matches.is_present("syslog") {
+        let mut format = SyslogFormat::default();
+        if let Some(name) = matches.value_of("severity") {
+            let level =
+                severity_level(name).ok_or_else(|| format!("unknown severity: {}", name))?;
+            format.max_severity = Some(level);
+        }
+        format.apps = matches
+            .values_of("app")
+            .unwrap_or_default()
+            .map(String::from)
+            .collect();
+        LineFormat::Syslog(format)
+    } else if matches.is_present("logfmt") {
+        LineFormat::Logfmt(fields)
+    } else if structured {
+        LineFormat::Json(fields)
+    } else {
+        LineFormat::Text
+    };
+    let color = match matches.value_of("color") {
+        Some("always") => true,
+        Some("auto") => io::stdout().is_terminal(),
+        _ => false,
+    };
+    let to = if matches.is_present("pretty") {
+        Some("pretty")
+    } else {
+        matches.value_of("to")
+    };
+    let record_output = match to {
+        Some("json") => RecordOutput::Json,
+        Some("logfmt") => RecordOutput::Logfmt,
+        Some(_) => {
+            let template = matches
+                .value_of("template")
+                .or(config.template.as_deref())
+                .unwrap_or(DEFAULT_TEMPLATE);
+            RecordOutput::Pretty(Pretty {
+                template: Template::parse(template)?,
+                dim: color,
+            })
+        }
+        None => RecordOutput::Same,
+    };
+    let converter = LineConverter {
+        format: &line_format,
+        regex_list: &regex_list,
+        conversion: &conversion,
+        output: &record_output,
+    };
+
+    let labels = match (matches.is_present("label"), color) {
+        (false, _) => Labels::None,
+        (true, true) => Labels::Colored,
+        (true, false) => Labels::Plain,
+    };
+
+    let jobs = match matches.value_of("jobs").unwrap_or_default().parse()? {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        jobs => jobs,
+    };
+    let stdout = io::stdout();
+    let mut out = BufWriter::with_capacity(OUTPUT_BUFFER, stdout.lock());
+    // Someone is watching: show each line as soon as it is converted.
+    let line_buffered = follow || io::stdout().is_terminal();
+
+    if inputs.len() == 1 && !matches.is_present("label") {
+        let (input, sorted) = open_input(
+            &inputs[0],
+            &mut stdin,
+            reading,
+            &window,
+            &regex_list,
+            &options,
+        )?;
+        if jobs > 1 && !follow {
+            info!("converting on {} threads", jobs);
+            let parallel = Parallel {
+                jobs,
+                converter,
+                options: &options,
+                invalid,
+                window,
+                sorted,
+            };
+            match input {
+                Input::Mapped(map, offset) => {
+                    parallel.run(&map.bytes()[offset as usize..], &mut out)?
+                }
+                Input::Streamed(reader) => parallel.run(reader, &mut out)?,
+            }
+            out.flush()?;
+            return Ok(());
+        }
+        let mut filter = WindowFilter::new(window);
+        // Converts and writes one line, returning false once the rest of a sorted input is
+        // past the window. Lines that do not change are written as they are, without copying.
+        let mut convert = |line: &[u8]| -> io::Result<bool> {
+            let (first, converted) = match converter.convert(line, &options) {
+                Some(converted) => converted,
+                None => return Ok(true),
+            };
+            if sorted && first.is_some_and(|dt| window.is_after(&dt)) {
+                return Ok(false);
+            }
+            if !filter.keep(first) {
+                return Ok(true);
+            }
+            out.write_all(&converted)?;
+            out.write_all(b"\n")?;
+            if line_buffered {
+                out.flush()?;
+            }
+            Ok(true)
+        };
+        match input {
+            Input::Mapped(map, offset) => {
+                for line in map.lines_from(offset) {
+                    if !convert(&invalid.apply(line))? {
+                        break;
+                    }
+                }
+            }
+            Input::Streamed(reader) => {
+                for (idx, line) in ByteLines::new(reader, invalid).enumerate() {
+                    let line = match line {
+                        Ok(line) => line,
+                        Err(e) if is_line_error(&e) => {
+                            report_skipped_line(&inputs[0], idx as u64 + 1, &e);
+                            continue;
+                        }
+                        Err(e) => return Err(e.into()),
+                    };
+                    if !convert(&line)? {
+                        break;
+                    }
+                }
+            }
+        }
+        out.flush()?;
+        return Ok(());
+    }
+
+    if follow {
+        let mut readers = Vec::with_capacity(inputs.len());
+        for input in &inputs {
+            let (reader, _) =
+                open_input(input, &mut stdin, reading, &window, &regex_list, &options)?;
+            readers.push((input.clone(), ByteLines::new(reader.into_reader(), invalid)));
+        }
+        follow::interleave(readers, converter, &options, &window, labels, &mut out)?;
+        return Ok(());
     }
+
+    let mut sources = Vec::with_capacity(inputs.len());
+    for input in &inputs {
+        let (reader, sorted) =
+            open_input(input, &mut stdin, reading, &window, &regex_list, &options)?;
+        sources.push(Source {
+            records: RecordReader::new(
+                input.clone(),
+                ByteLines::new(reader.into_reader(), invalid),
+                converter,
+                options.clone(),
+            ),
+            label: input.clone(),
+            sorted,
+        });
+    }
+    merge::merge(sources, &window, labels, &mut out)?;
+    out.flush()?;
     Ok(())
 }
 
-fn parse_timestamp(m: &str, pat: &DateTimePattern) -> Result<String, chrono::ParseError> {
-    let tz = chrono::Local;
-    let dt = if pat.is_naive {
-        let format = if pat.zulu {
-            &pat.format[..pat.format.len() - 1]
-        } else {
-            pat.format
-        };
-        let local = chrono::NaiveDateTime::parse_from_str(m, pat.format)?;
-        chrono::DateTime::<Utc>::from_utc(local, Utc)
-            .with_timezone(&tz)
-            .format(format!("{}%:z", format).as_str())
-            .to_string()
-    } else {
-        chrono::DateTime::parse_from_str(m, pat.format)?
-            .with_timezone(&tz)
-            .format(pat.format)
-            .to_string()
-    };
-    Ok(dt)
+/// Expands `input` as a glob pattern if it contains glob metacharacters.
+fn expand_input(input: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    if input == "-" || !input.contains(&['*', '?', '['][..]) {
+        return Ok(vec![input.to_string()]);
+    }
+    let mut paths = Vec::new();
+    for path in glob::glob(input)? {
+        paths.push(path?.to_string_lossy().into_owned());
+    }
+    if paths.is_empty() {
+        return Err(format!("no files match {}", input).into());
+    }
+    Ok(paths)
 }
 
-fn find_and_replace_timestamp(line: &str, regex_list: &[DateTimePattern]) -> String {
-    for pat in regex_list {
-        if let Some(m) = pat.regex.find(line) {
-            if let Ok(dt) = parse_timestamp(m.as_str(), pat) {
-                return line.replace(m.as_str(), dt.as_str());
-            }
+/// Stdin, opened the first time it is read so nothing waits on it before then.
+#[derive(Default)]
+struct Stdin {
+    reader: Option<Box<dyn BufRead + Send>>,
+    taken: bool,
+}
+
+impl Stdin {
+    /// Takes the reader, opening stdin the first time.
+    fn take(&mut self) -> io::Result<Box<dyn BufRead + Send>> {
+        if let Some(reader) = self.reader.take() {
+            return Ok(reader);
+        }
+        if self.taken {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "stdin can only be read once",
+            ));
         }
+        self.taken = true;
+        open_stdin()
+    }
+
+    /// Returns a reader from [`Stdin::take`], such as one that replays sampled lines.
+    fn put_back(&mut self, reader: Box<dyn BufRead + Send>) {
+        self.reader = Some(reader);
     }
-    line.to_string()
 }
 
-#[derive(Debug)]
-struct DateTimePattern<'a> {
-    format: &'a str,
-    regex: Regex,
-    is_naive: bool,
-    zulu: bool,
+/// Whether stdin is redirected from a regular file, so it can be sampled without waiting.
+#[cfg(unix)]
+fn stdin_is_file() -> bool {
+    use std::os::fd::AsFd;
+    io::stdin()
+        .as_fd()
+        .try_clone_to_owned()
+        .map(File::from)
+        .and_then(|file| file.metadata())
+        .is_ok_and(|metadata| metadata.is_file())
 }
 
-fn convert_dt_spec_regex(fmt: &str) -> Result<DateTimePattern, std::fmt::Error> {
-    let items = StrftimeItems::new(fmt);
-    let mut regex: String = "".to_string();
-    let mut is_naive = true;
-    let mut zulu = fmt.ends_with("Z") && !fmt.ends_with("%Z");
-    for item in items {
-        match item {
-            Item::Literal(s) => write!(regex, "{}", s)?,
-            Item::Space(_) => write!(regex, "\\s*")?,
-            Item::OwnedLiteral(ref s) => write!(regex, "{}", s)?,
-            Item::OwnedSpace(_) => write!(regex, "\\s*")?,
-            Item::Numeric(spec, pad) => {
-                use chrono::format::Numeric::*;
-                let width = match spec {
-                    Year | IsoYear => 4,
-                    YearDiv100 | YearMod100 | IsoYearDiv100 | IsoYearMod100 | Month | Day
-                    | WeekFromSun | WeekFromMon | IsoWeek | Hour | Hour12 | Minute | Second => 2,
-                    NumDaysFromSun | WeekdayFromMon => 1,
-                    Ordinal => 3,
-                    Nanosecond => 9,
-                    Timestamp => 1,
-                    Internal(_) => 0,
-                };
-                if pad == Pad::Space {
-                    write!(regex, "\\s{{0,{}}}\\d{{1,{}}}", width - 1, width)?
-                } else {
-                    write!(regex, "\\d{{{}}}", width)?
-                }
+#[cfg(not(unix))]
+fn stdin_is_file() -> bool {
+    false
+}
+
+/// Opens stdin, decoding it if it is compressed.
+fn open_stdin() -> io::Result<Box<dyn BufRead + Send>> {
+    let mut stdin = io::stdin();
+    let (compression, head) = decompress::sniff(&mut stdin)?;
+    if compression != decompress::Compression::None {
+        info!("stdin is {:?} compressed", compression);
+    }
+    let reader = decompress::decoder(compression, io::Cursor::new(head).chain(stdin))?;
+    Ok(Box::new(BufReader::new(reader)))
+}
+
+/// Opens a file from the start, decoding it if it is compressed.
+fn open_plain(input: &str) -> io::Result<Box<dyn BufRead + Send>> {
+    let mut file = File::open(input)?;
+    let (compression, head) = decompress::sniff(&mut file)?;
+    let reader = decompress::decoder(compression, io::Cursor::new(head).chain(file))?;
+    Ok(Box::new(BufReader::new(reader)))
+}
+
+/// How [`open_input`] reads files.
+#[derive(Debug, Clone, Copy, Default)]
+struct Reading {
+    /// Files are sorted by time, from `--bisect`.
+    bisect: bool,
+    /// Files are followed as they grow, from `--follow`.
+    follow: bool,
+    /// Regular files are mapped into memory, from `--mmap`.
+    mmap: bool,
+}
+
+/// An opened input: mapped into memory if `--mmap` was given for a plain regular file, streamed
+/// otherwise.
+enum Input {
+    /// The mapped file and the offset to start reading at.
+    Mapped(MappedFile, u64),
+    Streamed(Box<dyn BufRead + Send>),
+}
+
+impl Input {
+    fn into_reader(self) -> Box<dyn BufRead + Send> {
+        match self {
+            Input::Mapped(map, offset) => {
+                let mut cursor = io::Cursor::new(map);
+                cursor.set_position(offset);
+                Box::new(cursor)
             }
-            Item::Fixed(spec) => {
-                use chrono::format::Fixed::*;
-                match spec {
-                    ShortMonthName => write!(regex, "{}", SHORT_MONTHS)?,
-                    LongMonthName => write!(regex, "{}", LONG_MONTHS)?,
-                    ShortWeekdayName => write!(regex, "{}", SHORT_WEEKDAYS)?,
-                    LongWeekdayName => write!(regex, "{}", LONG_WEEKDAYS)?,
-                    LowerAmPm => write!(regex, "{}", LOWER_AM_PM)?,
-                    UpperAmPm => write!(regex, "{}", UPPER_AM_PM)?,
-                    Nanosecond => write!(
-                        regex,
-                        r"\.({}|{}|{})",
-                        THREE_DIGITS, SIX_DIGITS, NINE_DIGITS
-                    )?,
-                    Nanosecond3 => write!(regex, r"\.\d{{3}}")?,
-                    Nanosecond6 => write!(regex, r"\.\d{{6}}")?,
-                    Nanosecond9 => write!(regex, r"\.\d{{9}}")?,
-                    TimezoneName => todo!(),
-                    TimezoneOffsetColon => {
-                        is_naive = false;
-                        write!(regex, r"[+-]\d{{2}}:\d{{2}}")?;
-                    }
-                    TimezoneOffsetColonZ => {
-                        write!(regex, r"(?:Z|[+-]\d{{2}}:\d{{2}})")?;
-                        is_naive = false;
-                        zulu = true;
-                    }
-                    TimezoneOffset => {
-                        write!(regex, r"[+-]\d{{2}}\d{{2}}")?;
-                        is_naive = false;
-                    }
-                    TimezoneOffsetZ => {
-                        write!(regex, r"(?Z|[+-]\d{{2}}\d{{2}})")?;
-                        is_naive = false;
-                        zulu = true;
-                    }
-                    RFC2822 => {
-                        let dt = format!(
-                            r"{short_weekday},\s+{two_digit}\s+{month}\s+{four_digit}\s+{two_digit}:{two_digit}:{two_digit} [+-]{two_digit}{two_digit}",
-                            short_weekday = SHORT_WEEKDAYS,
-                            month = SHORT_MONTHS,
-                            four_digit = FOUR_DIGITS,
-                            two_digit = TWO_DIGITS,
-                        );
-                        write!(regex, "{}", dt)?;
-                        is_naive = false;
-                    }
-                    RFC3339 => {
-                        let dt = format!(
-                            r"{four_digit}-{two_digit}-{two_digit}T{two_digit}:{two_digit}:{two_digit}\.{nano}",
-                            two_digit = TWO_DIGITS,
-                            four_digit = FOUR_DIGITS,
-                            nano = NANO_SECOND_REGEX,
-                        );
-                        write!(regex, "{}", dt)?;
-                        is_naive = false;
-                    }
-                    Internal(_) => todo!(),
+            Input::Streamed(reader) => reader,
+        }
+    }
+}
+
+/// Opens a file, or takes `stdin` for `-`, returning whether it is known to be time-sorted.
+///
+/// With `bisect` set, regular files are positioned at the start of the window and treated as
+/// sorted unless probing shows otherwise. With `follow` set, files are read like `tail -F`, and
+/// with `mmap` set, other regular files are mapped into memory rather than streamed.
+/// Compressed inputs, including stdin, are recognised by their magic bytes and decoded.
+fn open_input(
+    input: &str,
+    stdin: &mut Stdin,
+    reading: Reading,
+    window: &TimeWindow,
+    regex_list: &PatternSet,
+    options: &ParseOptions,
+) -> io::Result<(Input, bool)> {
+    if input == "-" {
+        return Ok((Input::Streamed(stdin.take()?), false));
+    }
+    let mut file = File::open(input)?;
+    let seekable = file.metadata()?.is_file();
+    let (compression, head) = decompress::sniff(&mut file)?;
+    if compression != decompress::Compression::None {
+        info!("{} is {:?} compressed", input, compression);
+    }
+    if compression != decompress::Compression::None || !seekable {
+        // Compressed data cannot be bisected or followed, but a sorted archive can still
+        // stop at --until.
+        let sorted = reading.bisect && compression != decompress::Compression::None;
+        let reader = decompress::decoder(compression, io::Cursor::new(head).chain(file))?;
+        return Ok((Input::Streamed(Box::new(BufReader::new(reader))), sorted));
+    }
+    file.seek(SeekFrom::Start(0))?;
+    let mut sorted = false;
+    if reading.bisect {
+        sorted = true;
+        if let Some(since) = window.since {
+            match seek::bisect(&mut file, &since, regex_list, options)? {
+                seek::Bisection::Sorted(offset) => {
+                    info!("{}: window starts after byte {}", input, offset);
+                    file.seek(SeekFrom::Start(offset))?;
+                }
+                seek::Bisection::Unsorted => {
+                    warn!("{} is not sorted by time, scanning it all", input);
+                    file.seek(SeekFrom::Start(0))?;
+                    sorted = false;
                 }
             }
-            Item::Error => todo!(),
         }
     }
-    println!("Regex: {}", regex);
-    Ok(DateTimePattern {
-        format: fmt,
-        regex: Regex::new(&regex).unwrap(),
-        is_naive,
-        zulu,
-    })
+    if reading.follow {
+        let reader = follow::FollowReader::new(input.into(), file)?;
+        return Ok((Input::Streamed(Box::new(BufReader::new(reader))), sorted));
+    }
+    if !reading.mmap {
+        return Ok((Input::Streamed(Box::new(BufReader::new(file))), sorted));
+    }
+    let offset = file.stream_position()?;
+    match MappedFile::new(&file) {
+        Ok(map) => Ok((Input::Mapped(map, offset), sorted)),
+        Err(e) => {
+            debug!("cannot map {} ({}), reading it instead", input, e);
+            Ok((Input::Streamed(Box::new(BufReader::new(file))), sorted))
+        }
+    }
 }
@@ -0,0 +1,191 @@
+use chrono::{DateTime, FixedOffset};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{self, prelude::*};
+
+use crate::diag::report_skipped_line;
+use crate::lines::is_line_error;
+use crate::lines::ByteLines;
+use logzen::window::TimeWindow;
+use logzen::{LineConverter, ParseOptions};
+
+/// Colours cycled through for source labels.
+const LABEL_COLORS: [u8; 6] = [36, 33, 35, 32, 34, 31];
+
+/// A timestamped line together with the lines without a timestamp that follow it, already
+/// converted for output.
+#[derive(Debug)]
+pub struct Record {
+    pub time: Option<DateTime<FixedOffset>>,
+    pub lines: Vec<Vec<u8>>,
+}
+
+/// A converted line and the time of its first timestamp, if it has one.
+type TimedLine = (Option<DateTime<FixedOffset>>, Vec<u8>);
+
+/// Groups the lines of one input into [`Record`]s.
+///
+/// Each reader keeps its own [`ParseOptions`] so state such as year inference follows the
+/// file it belongs to. Lines that cannot be decoded are reported and skipped.
+pub struct RecordReader<'p> {
+    name: String,
+    line_number: u64,
+    lines: ByteLines<Box<dyn BufRead>>,
+    converter: LineConverter<'p>,
+    options: ParseOptions,
+    pending: Option<TimedLine>,
+}
+
+impl<'p> RecordReader<'p> {
+    pub fn new(
+        name: String,
+        lines: ByteLines<Box<dyn BufRead>>,
+        converter: LineConverter<'p>,
+        options: ParseOptions,
+    ) -> RecordReader<'p> {
+        RecordReader {
+            name,
+            line_number: 0,
+            lines,
+            converter,
+            options,
+            pending: None,
+        }
+    }
+
+    fn read_line(&mut self) -> Option<io::Result<TimedLine>> {
+        loop {
+            self.line_number += 1;
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) if is_line_error(&e) => {
+                    report_skipped_line(&self.name, self.line_number, &e);
+                    continue;
+                }
+                Err(e) => return Some(Err(e)),
+            };
+            if let Some((time, converted)) = self.converter.convert(&line, &self.options) {
+                return Some(Ok((time, converted.into_owned())));
+            }
+        }
+    }
+}
+
+impl Iterator for RecordReader<'_> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<io::Result<Record>> {
+        let (time, first) = match self.pending.take() {
+            Some(pending) => pending,
+            None => match self.read_line()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            },
+        };
+        let mut record = Record {
+            time,
+            lines: vec![first],
+        };
+        while let Some(next) = self.read_line() {
+            match next {
+                Ok((None, line)) => record.lines.push(line),
+                Ok(line) => {
+                    self.pending = Some(line);
+                    break;
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        Some(Ok(record))
+    }
+}
+
+/// One input to [`merge`].
+pub struct Source<'p> {
+    pub records: RecordReader<'p>,
+    /// Text shown before each line when labels are enabled.
+    pub label: String,
+    /// The input is known to be time-sorted, so it can stop at the end of the window.
+    pub sorted: bool,
+}
+
+/// How lines are attributed to their source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Labels {
+    None,
+    Plain,
+    Colored,
+}
+
+impl Labels {
+    /// Builds the prefix written before each line of the source named `names[i]`, padded so
+    /// the lines of all sources line up.
+    pub fn prefixes(self, names: &[String]) -> Vec<String> {
+        let width = names.iter().map(|name| name.len()).max().unwrap_or(0);
+        names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| match self {
+                Labels::None => String::new(),
+                Labels::Plain => format!("{:width$} | ", name, width = width),
+                Labels::Colored => format!(
+                    "\x1b[{}m{:width$}\x1b[0m | ",
+                    LABEL_COLORS[idx % LABEL_COLORS.len()],
+                    name,
+                    width = width
+                ),
+            })
+            .collect()
+    }
+}
+
+/// Writes the records of all `sources` interleaved by time, like a k-way merge.
+///
+/// Records without a time sort first and records with equal times keep the order of
+/// `sources`, so the output is stable. Records outside `window` are dropped.
+pub fn merge<W: Write>(
+    sources: Vec<Source>,
+    window: &TimeWindow,
+    labels: Labels,
+    out: &mut W,
+) -> io::Result<()> {
+    let names: Vec<String> = sources.iter().map(|s| s.label.clone()).collect();
+    let prefixes = labels.prefixes(&names);
+
+    let mut sources = sources;
+    let mut heads: Vec<Option<Record>> = Vec::with_capacity(sources.len());
+    let mut heap = BinaryHeap::new();
+    for (idx, source) in sources.iter_mut().enumerate() {
+        let head = next_record(source, window)?;
+        if let Some(record) = &head {
+            heap.push(Reverse((record.time, idx)));
+        }
+        heads.push(head);
+    }
+
+    while let Some(Reverse((_, idx))) = heap.pop() {
+        let record = heads[idx].take().expect("heap entry without a record");
+        if window.is_unbounded() || record.time.is_some_and(|t| window.contains(&t)) {
+            for line in &record.lines {
+                out.write_all(prefixes[idx].as_bytes())?;
+                out.write_all(line)?;
+                out.write_all(b"\n")?;
+            }
+        }
+        let head = next_record(&mut sources[idx], window)?;
+        if let Some(record) = &head {
+            heap.push(Reverse((record.time, idx)));
+        }
+        heads[idx] = head;
+    }
+    Ok(())
+}
+
+fn next_record(source: &mut Source, window: &TimeWindow) -> io::Result<Option<Record>> {
+    match source.records.next().transpose()? {
+        Some(record) if source.sorted && record.time.is_some_and(|t| window.is_after(&t)) => {
+            Ok(None)
+        }
+        record => Ok(record),
+    }
+}
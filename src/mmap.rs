@@ -0,0 +1,65 @@
+use memmap2::Mmap;
+use std::fs::File;
+use std::io;
+
+/// A regular file mapped into memory, so its lines can be read as slices without copying.
+///
+/// Truncating a mapped file kills the process with `SIGBUS`, which live logs risk whenever
+/// `logrotate` rotates them with `copytruncate`, so files are only mapped when `--mmap` asks
+/// for it and never when they are followed.
+pub struct MappedFile {
+    map: Mmap,
+}
+
+impl MappedFile {
+    /// Maps `file`, which must be a regular file.
+    pub fn new(file: &File) -> io::Result<MappedFile> {
+        // Safety: the mapping is only read, but nothing stops another process from changing
+        // the file underneath it. Writes past the end are harmless; truncation is not, as
+        // touching a page past the new end raises SIGBUS rather than an error. That is the
+        // case for a live log rotated with `logrotate copytruncate` while it is read, so
+        // callers only map files when the user opted in with `--mmap`, and never when following.
+        let map = unsafe { Mmap::map(file)? };
+        Ok(MappedFile { map })
+    }
+
+    /// The whole file.
+    pub fn bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    /// Lines starting at byte `offset`, without their trailing `\n`.
+    pub fn lines_from(&self, offset: u64) -> MappedLines<'_> {
+        let offset = (offset as usize).min(self.map.len());
+        MappedLines {
+            rest: &self.map[offset..],
+        }
+    }
+}
+
+impl AsRef<[u8]> for MappedFile {
+    fn as_ref(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+/// Lines of a [`MappedFile`], borrowed from the mapping.
+pub struct MappedLines<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Iterator for MappedLines<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let (line, rest) = match memchr::memchr(b'\n', self.rest) {
+            Some(end) => (&self.rest[..end], &self.rest[end + 1..]),
+            None => (self.rest, &self.rest[self.rest.len()..]),
+        };
+        self.rest = rest;
+        Some(line)
+    }
+}
@@ -0,0 +1,273 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+use std::collections::BTreeMap;
+use std::io::{self, prelude::*};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use crate::lines::{ByteLines, InvalidUtf8};
+use logzen::window::{TimeWindow, WindowFilter};
+use logzen::{LineConverter, ParseOptions, YearInference};
+
+/// Bytes of input handed to a worker at a time, extended to the end of the last line.
+const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// A converted chunk: its text with every line's end offset and first timestamp.
+struct Converted {
+    text: Vec<u8>,
+    lines: Vec<(usize, Option<DateTime<FixedOffset>>)>,
+    /// The input, kept in case the chunk has to be converted again.
+    chunk: Vec<u8>,
+    /// The first and last timestamps whose year was inferred.
+    first_inferred: Option<NaiveDateTime>,
+    last_inferred: Option<NaiveDateTime>,
+}
+
+/// Converts one input on several threads while keeping the output in input order.
+///
+/// The input is cut into chunks on line boundaries, workers find and convert the timestamps
+/// in each chunk, and the results are written back in order. Window filtering runs as chunks
+/// are written, so lines without a timestamp still follow the line before them.
+///
+/// Each chunk starts with fresh parse state. A missing year is inferred per chunk, and if the
+/// year of a chunk's first such timestamp does not follow on from the chunk before it, as at
+/// a December to January rollover, the chunk is converted again in order before it is written.
+/// The output is the same as converting the input on one thread.
+pub struct Parallel<'a> {
+    /// Worker threads to run.
+    pub jobs: usize,
+    pub converter: LineConverter<'a>,
+    pub options: &'a ParseOptions,
+    pub invalid: InvalidUtf8,
+    pub window: TimeWindow,
+    /// The input is time-sorted, so it can stop at the end of the window.
+    pub sorted: bool,
+}
+
+impl Parallel<'_> {
+    pub fn run<R, W>(&self, reader: R, out: &mut W) -> io::Result<()>
+    where
+        R: BufRead + Send,
+        W: Write,
+    {
+        let (job_sender, job_receiver) = mpsc::sync_channel::<(u64, Vec<u8>)>(self.jobs * 2);
+        let (result_sender, result_receiver) = mpsc::sync_channel(self.jobs * 2);
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        thread::scope(|scope| {
+            let reading = scope.spawn(move || read_chunks(reader, job_sender));
+            for _ in 0..self.jobs {
+                let jobs = Arc::clone(&job_receiver);
+                let results = result_sender.clone();
+                let options = self.options.clone();
+                let (converter, invalid) = (self.converter, self.invalid);
+                scope.spawn(move || loop {
+                    let job = jobs.lock().expect("job queue poisoned").recv();
+                    let (seq, chunk) = match job {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    options.years.restart();
+                    let converted = convert(chunk, converter, &options, invalid);
+                    if results.send((seq, converted)).is_err() {
+                        break;
+                    }
+                });
+            }
+            // Only the workers may hold the job queue: once they stop, the reader's next send
+            // fails instead of blocking on a full queue.
+            drop(job_receiver);
+            drop(result_sender);
+
+            // Dropping the receiver on an early return stops the workers, which in turn
+            // stops the reader.
+            self.write_in_order(result_receiver, out)?;
+            reading.join().expect("reader thread panicked")
+        })
+    }
+
+    fn write_in_order<W: Write>(
+        &self,
+        results: mpsc::Receiver<(u64, Converted)>,
+        out: &mut W,
+    ) -> io::Result<()> {
+        let mut filter = WindowFilter::new(self.window);
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        let options = self.options.clone();
+        // The last year-inferred timestamp written so far.
+        let mut carried = None;
+        for (seq, converted) in results {
+            pending.insert(seq, converted);
+            while let Some(mut converted) = pending.remove(&next) {
+                next += 1;
+                if let (Some(last), Some(first)) = (carried, converted.first_inferred) {
+                    if !YearInference::agrees(last, first) {
+                        options.years.continue_from(last);
+                        converted =
+                            convert(converted.chunk, self.converter, &options, self.invalid);
+                    }
+                }
+                carried = converted.last_inferred.or(carried);
+                let mut start = 0;
+                for &(end, time) in &converted.lines {
+                    if self.sorted && time.is_some_and(|t| self.window.is_after(&t)) {
+                        return Ok(());
+                    }
+                    if filter.keep(time) {
+                        out.write_all(&converted.text[start..end])?;
+                        out.write_all(b"\n")?;
+                    }
+                    start = end;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn convert(
+    chunk: Vec<u8>,
+    converter: LineConverter,
+    options: &ParseOptions,
+    invalid: InvalidUtf8,
+) -> Converted {
+    let mut converted = Converted {
+        text: Vec::with_capacity(chunk.len() + chunk.len() / 8),
+        lines: Vec::new(),
+        chunk: Vec::new(),
+        first_inferred: None,
+        last_inferred: None,
+    };
+    for line in ByteLines::new(chunk.as_slice(), invalid) {
+        // Reading from memory cannot fail.
+        let line = line.unwrap_or_default();
+        let (time, text) = match converter.convert(&line, options) {
+            Some(converted) => converted,
+            None => continue,
+        };
+        converted.text.extend_from_slice(&text);
+        converted.lines.push((converted.text.len(), time));
+    }
+    converted.chunk = chunk;
+    converted.first_inferred = options.years.first();
+    converted.last_inferred = options.years.last();
+    converted
+}
+
+/// Cuts `reader` into numbered chunks that end on a line boundary.
+fn read_chunks<R: BufRead>(
+    mut reader: R,
+    jobs: mpsc::SyncSender<(u64, Vec<u8>)>,
+) -> io::Result<()> {
+    for seq in 0.. {
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE as usize + 4096);
+        (&mut reader).take(CHUNK_SIZE).read_to_end(&mut chunk)?;
+        if chunk.is_empty() {
+            break;
+        }
+        if chunk.last() != Some(&b'\n') {
+            reader.read_until(b'\n', &mut chunk)?;
+        }
+        if jobs.send((seq, chunk)).is_err() {
+            // The writer stopped early.
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use logzen::{
+        convert_dt_spec_regex, Conversion, LineFormat, OutputFormat, PatternSet, RecordOutput,
+        TargetZone,
+    };
+    use std::time::Duration;
+
+    /// A writer that fails like stdout does once the reader of a pipe has gone.
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn infers_years_across_chunks_like_one_thread() {
+        let regex_list = PatternSet::new(vec![convert_dt_spec_regex("%b %e %H:%M:%S").unwrap()]);
+        let conversion = Conversion {
+            zone: TargetZone::from_name("UTC").unwrap(),
+            output: OutputFormat::Strftime("%Y-%m-%d".to_string()),
+        };
+        let converter = LineConverter {
+            format: &LineFormat::Text,
+            regex_list: &regex_list,
+            conversion: &conversion,
+            output: &RecordOutput::Same,
+        };
+        let mut options = ParseOptions::default();
+        options.years.start = Some(2023);
+        // Several chunks on each side of the new year.
+        let mut input = "Dec 31 23:59:59 padding to fill the chunks\n".repeat(100_000);
+        input.push_str(&"Jan  1 00:00:00 padding to fill the chunks\n".repeat(100_000));
+
+        let mut expected = Vec::new();
+        for line in input.lines() {
+            expected.extend_from_slice(&converter.convert(line.as_bytes(), &options).unwrap().1);
+            expected.push(b'\n');
+        }
+        options.years.restart();
+        let parallel = Parallel {
+            jobs: 4,
+            converter,
+            options: &options,
+            invalid: InvalidUtf8::Passthrough,
+            window: TimeWindow::default(),
+            sorted: false,
+        };
+        let mut output = Vec::new();
+        parallel.run(input.as_bytes(), &mut output).unwrap();
+        assert!(output.ends_with(b"2024-01-01 padding to fill the chunks\n"));
+        assert!(output == expected);
+    }
+
+    #[test]
+    fn stops_when_the_writer_fails() {
+        let (done, finished) = mpsc::channel();
+        thread::spawn(move || {
+            let regex_list = PatternSet::new(vec![convert_dt_spec_regex("%+").unwrap()]);
+            let conversion = Conversion {
+                zone: TargetZone::from_name("UTC").unwrap(),
+                output: OutputFormat::Input,
+            };
+            let converter = LineConverter {
+                format: &LineFormat::Text,
+                regex_list: &regex_list,
+                conversion: &conversion,
+                output: &RecordOutput::Same,
+            };
+            let parallel = Parallel {
+                jobs: 2,
+                converter,
+                options: &ParseOptions::default(),
+                invalid: InvalidUtf8::Passthrough,
+                window: TimeWindow::default(),
+                sorted: false,
+            };
+            // Far more chunks than the queues between the threads can hold.
+            let input = "2026-10-15T10:00:00Z line\n".repeat(1_000_000);
+            let result = parallel.run(input.as_bytes(), &mut ClosedPipe);
+            done.send(result.map_err(|e| e.kind())).unwrap();
+        });
+        let result = finished
+            .recv_timeout(Duration::from_secs(60))
+            .expect("Parallel::run did not return after the writer failed");
+        assert_eq!(result, Err(io::ErrorKind::BrokenPipe));
+    }
+}
@@ -0,0 +1,295 @@
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDateTime, TimeZone, Utc};
+use std::cell::Cell;
+
+use crate::json::DEFAULT_TIME_KEYS;
+use crate::pattern::{DateTimePattern, PatternSet};
+use crate::zone::{fix_offset, ZoneAbbreviations};
+
+/// Epoch numbers in seconds (optionally fractional), milliseconds, microseconds or nanoseconds.
+pub(crate) const EPOCH_REGEX: &str = r"\b(?P<epoch>\d{19}|\d{16}|\d{13}|\d{10}(?:\.\d{1,9})?)\b";
+/// Epochs outside 2000-01-01..2100-01-01 are assumed to be some other number.
+pub(crate) const EPOCH_MIN_SECS: i64 = 946_684_800;
+pub(crate) const EPOCH_MAX_SECS: i64 = 4_102_444_800;
+
+/// Which epoch numbers are recognised as timestamps.
+#[derive(Debug, Clone, Default)]
+pub struct EpochOptions {
+    /// Keys whose values may be epochs; empty means the usual time keys, [`DEFAULT_TIME_KEYS`].
+    pub keys: Vec<String>,
+    /// Accept epochs that are the value of any key, unless `keys` are given.
+    pub any_key: bool,
+    /// Accept epochs that are not the value of a `key=` or `"key":` pair, and under any key
+    /// unless `keys` are given.
+    pub anywhere: bool,
+}
+
+impl EpochOptions {
+    /// Decides from the text preceding a match whether an epoch there should be converted.
+    pub fn allows(&self, prefix: &str) -> bool {
+        match epoch_key(prefix) {
+            Some(key) if self.keys.is_empty() => {
+                self.any_key || self.anywhere || DEFAULT_TIME_KEYS.contains(&key)
+            }
+            Some(key) => self.keys.iter().any(|k| k == key),
+            None => self.anywhere && self.keys.is_empty(),
+        }
+    }
+}
+
+/// Returns the key a value starting right after `prefix` belongs to, for `key=`, `key: ` and
+/// `"key":` (optionally with the value itself quoted).
+fn epoch_key(prefix: &str) -> Option<&str> {
+    let p = prefix.strip_suffix('"').unwrap_or(prefix).trim_end();
+    let p = p
+        .strip_suffix('=')
+        .or_else(|| p.strip_suffix(':'))?
+        .trim_end();
+    let p = p.strip_suffix('"').unwrap_or(p);
+    let start = p
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !(c.is_alphanumeric() || "_-.@".contains(*c)))
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    if start == p.len() {
+        None
+    } else {
+        Some(&p[start..])
+    }
+}
+
+/// Converts an epoch number, picking its unit from the digit count.
+pub(crate) fn parse_epoch(value: &str) -> Option<DateTime<FixedOffset>> {
+    let mut parts = value.splitn(2, '.');
+    let whole = parts.next()?;
+    let number: i64 = whole.parse().ok()?;
+    let (secs, nanos) = match whole.len() {
+        10 => {
+            let fraction = parts.next().unwrap_or("");
+            let nanos = if fraction.is_empty() {
+                0
+            } else {
+                format!("{:0<9}", fraction).parse().ok()?
+            };
+            (number, nanos)
+        }
+        13 => (number / 1_000, (number % 1_000) as u32 * 1_000_000),
+        16 => (number / 1_000_000, (number % 1_000_000) as u32 * 1_000),
+        19 => (number / 1_000_000_000, (number % 1_000_000_000) as u32),
+        _ => return None,
+    };
+    if !(EPOCH_MIN_SECS..EPOCH_MAX_SECS).contains(&secs) {
+        return None;
+    }
+    Utc.timestamp_opt(secs, nanos).single().map(fix_offset)
+}
+
+/// Supplies the year for formats that leave it out, such as BSD syslog's `%b %e %H:%M:%S`.
+///
+/// The first timestamp gets the configured year, or the current one unless that would put it
+/// more than a day in the future. After that the year only moves forward: a timestamp more than
+/// half a year earlier than the previous one is taken to be a December to January rollover.
+#[derive(Debug, Clone, Default)]
+pub struct YearInference {
+    /// Year given to the first timestamp; the current year when unset.
+    pub start: Option<i32>,
+    last: Cell<Option<NaiveDateTime>>,
+    /// The first timestamp inferred without a previous one, so input converted in pieces can
+    /// be checked against the piece before it.
+    first: Cell<Option<NaiveDateTime>>,
+}
+
+impl YearInference {
+    fn infer<F>(&self, parse: F) -> Option<NaiveDateTime>
+    where
+        F: Fn(i32) -> Option<NaiveDateTime>,
+    {
+        let dt = match (self.last.get(), self.start) {
+            (Some(last), _) => {
+                let dt = parse(last.year())?;
+                if dt < last - chrono::Duration::days(183) {
+                    parse(last.year() + 1)?
+                } else {
+                    dt
+                }
+            }
+            (None, Some(year)) => parse(year)?,
+            (None, None) => {
+                let now = Utc::now().naive_utc();
+                let dt = parse(now.year())?;
+                if dt > now + chrono::Duration::days(1) {
+                    parse(now.year() - 1)?
+                } else {
+                    dt
+                }
+            }
+        };
+        if self.last.get().is_none() {
+            self.first.set(Some(dt));
+        }
+        self.last.set(Some(dt));
+        Some(dt)
+    }
+
+    /// Forgets the timestamps seen so far, so the next one is inferred as if it came first.
+    pub fn restart(&self) {
+        self.last.set(None);
+        self.first.set(None);
+    }
+
+    /// Infers the following timestamps as if they came right after `last`.
+    pub fn continue_from(&self, last: NaiveDateTime) {
+        self.last.set(Some(last));
+        self.first.set(None);
+    }
+
+    /// The first timestamp inferred since the last restart without a timestamp before it.
+    pub fn first(&self) -> Option<NaiveDateTime> {
+        self.first.get()
+    }
+
+    /// The last timestamp inferred.
+    pub fn last(&self) -> Option<NaiveDateTime> {
+        self.last.get()
+    }
+
+    /// Whether `dt`, inferred without a previous timestamp, gets the same year when it
+    /// follows `last` instead.
+    pub fn agrees(last: NaiveDateTime, dt: NaiveDateTime) -> bool {
+        let follows = match dt.with_year(last.year()) {
+            Some(same) if same < last - chrono::Duration::days(183) => {
+                dt.with_year(last.year() + 1)
+            }
+            same => same,
+        };
+        follows == Some(dt)
+    }
+}
+
+/// Settings that affect how matched text is interpreted as a point in time.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// The zones `%Z` abbreviations stand for.
+    pub abbreviations: ZoneAbbreviations,
+    /// Which epoch numbers are timestamps.
+    pub epochs: EpochOptions,
+    /// The year for formats without one.
+    pub years: YearInference,
+}
+
+fn with_year(m: &str, format: &str, year: i32) -> (String, String) {
+    (format!("{} {}", year, m), format!("%Y {}", format))
+}
+
+fn parse_naive(m: &str, pat: &DateTimePattern, options: &ParseOptions) -> Option<NaiveDateTime> {
+    if pat.has_year {
+        return NaiveDateTime::parse_from_str(m, &pat.format).ok();
+    }
+    options.years.infer(|year| {
+        let (m, format) = with_year(m, &pat.format, year);
+        NaiveDateTime::parse_from_str(&m, &format).ok()
+    })
+}
+
+/// Parses text matched by `pat` into a point in time.
+///
+/// Times without an offset are taken to be UTC, `%Z` abbreviations are resolved through
+/// `options.abbreviations` and a missing year is inferred through `options.years`.
+pub fn parse_timestamp(
+    m: &str,
+    pat: &DateTimePattern,
+    options: &ParseOptions,
+) -> Option<DateTime<FixedOffset>> {
+    if pat.epoch {
+        let captures = pat.regex.captures(m.as_bytes())?;
+        return parse_epoch(std::str::from_utf8(captures.name("epoch")?.as_bytes()).ok()?);
+    }
+    let dt = if pat.zone_name {
+        let naive = parse_naive(m, pat, options)?;
+        let abbr = pat
+            .regex
+            .captures(m.as_bytes())
+            .and_then(|c| c.name("tzname"))
+            .and_then(|name| std::str::from_utf8(name.as_bytes()).ok())
+            .unwrap_or("");
+        options.abbreviations.resolve(abbr, &naive)?
+    } else if pat.is_naive {
+        let naive = parse_naive(m, pat, options)?;
+        fix_offset(Utc.from_utc_datetime(&naive))
+    } else if pat.has_year {
+        DateTime::parse_from_str(m, &pat.format).ok()?
+    } else {
+        let year = parse_naive(m, pat, options)?.year();
+        let (m, format) = with_year(m, &pat.format, year);
+        DateTime::parse_from_str(&m, &format).ok()?
+    };
+    Some(dt)
+}
+
+/// A timestamp found in a line: its byte span, parsed value and the pattern that matched it.
+#[derive(Debug)]
+pub struct TimestampMatch<'p> {
+    /// Byte offset of the first byte of the timestamp.
+    pub start: usize,
+    /// Byte offset just past the timestamp.
+    pub end: usize,
+    /// The time the text stands for.
+    pub datetime: DateTime<FixedOffset>,
+    /// The pattern that matched it.
+    pub pattern: &'p DateTimePattern,
+}
+
+/// Finds every non-overlapping timestamp in `line`, left to right.
+///
+/// Where several patterns match at the same position the longest match wins, with ties going
+/// to the pattern that comes first in `regex_list`. Candidates that fail to parse are skipped so
+/// a shorter or later match can take their place. `line` need not be valid UTF-8 outside the
+/// timestamps themselves.
+pub fn find_timestamps<'p>(
+    line: &[u8],
+    regex_list: &'p PatternSet,
+    options: &ParseOptions,
+) -> Vec<TimestampMatch<'p>> {
+    let patterns = regex_list.patterns();
+    let mut candidates: Vec<(usize, usize, usize)> = regex_list
+        .matching(line)
+        .into_iter()
+        .flat_map(|idx| {
+            patterns[idx]
+                .regex
+                .find_iter(line)
+                .map(move |m| (m.start(), m.end(), idx))
+        })
+        .filter(|(start, end, _)| start < end)
+        .collect();
+    candidates.sort_by_key(|&(start, end, idx)| (start, std::cmp::Reverse(end), idx));
+
+    let mut found = Vec::new();
+    let mut cursor = 0;
+    for (start, end, idx) in candidates {
+        if start < cursor {
+            continue;
+        }
+        let pattern = &patterns[idx];
+        if pattern.epoch
+            && !options
+                .epochs
+                .allows(&String::from_utf8_lossy(&line[..start]))
+        {
+            continue;
+        }
+        let text = match std::str::from_utf8(&line[start..end]) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        if let Some(datetime) = parse_timestamp(text, pattern, options) {
+            found.push(TimestampMatch {
+                start,
+                end,
+                datetime,
+                pattern,
+            });
+            cursor = end;
+        }
+    }
+    found
+}
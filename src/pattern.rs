@@ -0,0 +1,291 @@
+use chrono::format::{Item, Pad, StrftimeItems};
+use regex::bytes::{Regex, RegexSet};
+
+use crate::error::PatternError;
+use crate::parse::EPOCH_REGEX;
+
+const LONG_MONTHS: &str =
+    "January|February|March|April|May|June|July|August|September|October|November|December";
+
+const SHORT_WEEKDAYS: &str = "Mon|Tue|Wed|Thu|Fri|Sat|Sun";
+const LONG_WEEKDAYS: &str = "Monday|Tuesday|Wednesday|Thursday|Friday|Saturday|Sunday";
+const LOWER_AM_PM: &str = "am|pm";
+const UPPER_AM_PM: &str = "AM|PM";
+const SHORT_MONTHS: &str = "Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Oct|Nov|Dec";
+
+const TWO_DIGITS: &str = r"\d{2}";
+const FOUR_DIGITS: &str = r"\d{4}";
+/// An optional fraction of a second; chrono accepts any number of digits.
+const FRACTION_REGEX: &str = r"(?:\.\d{1,9})?";
+/// Formats tried after any user-supplied ones.
+///
+/// The year-less syslog format is only looked for at the start of a line, where syslog writes
+/// it; elsewhere it would match inside longer timestamps such as `date`'s.
+pub const DEFAULT_FORMATS: [&str; 7] = [
+    "%+",
+    "%c",
+    "%a %b %e %H:%M:%S %Z %Y",
+    "%Y-%m-%dT%H:%M:%SZ",
+    "%Y-%m-%dT%H:%M:%S%z",
+    "%s",
+    "^%b %e %H:%M:%S",
+];
+
+/// A strftime format compiled into a regex that finds text in that format.
+///
+/// The regex works on bytes, so it can search lines that are not valid UTF-8.
+#[derive(Debug)]
+pub struct DateTimePattern {
+    pub(crate) format: String,
+    pub(crate) regex: Regex,
+    pub(crate) is_naive: bool,
+    pub(crate) zulu: bool,
+    /// The format contains `%Z`; the abbreviation is captured as `tzname`.
+    pub(crate) zone_name: bool,
+    /// The format contains `%s`; the epoch number is captured as `epoch`.
+    pub(crate) epoch: bool,
+    /// The format names the year; if not, it is inferred through [`crate::YearInference`].
+    pub(crate) has_year: bool,
+}
+
+impl DateTimePattern {
+    /// The strftime format this pattern was compiled from, without a leading `^`.
+    pub fn format(&self) -> &str {
+        &self.format
+    }
+
+    /// The regex that finds text in this format.
+    pub fn regex(&self) -> &Regex {
+        &self.regex
+    }
+
+    /// Whether matched text carries no zone information and is read as UTC.
+    pub fn is_naive(&self) -> bool {
+        self.is_naive
+    }
+
+    /// Whether the format ends in a literal or optional `Z` marking UTC.
+    pub fn is_zulu(&self) -> bool {
+        self.zulu
+    }
+}
+
+/// Compiled patterns that are searched together.
+///
+/// A [`RegexSet`] over all patterns finds which of them can match a line in a single pass, so
+/// only those are run on their own.
+///
+/// They are run in priority order rather than by how often they have matched. Ordering by hit
+/// rate cannot save work in [`crate::find_timestamps`], where every matching pattern has to run
+/// for the longest match to win, and where the first success is taken instead, as for a JSON
+/// field that is a whole timestamp, it would let earlier lines decide which of two overlapping
+/// formats reads a value, so output would no longer follow the configured priority.
+#[derive(Debug)]
+pub struct PatternSet {
+    patterns: Vec<DateTimePattern>,
+    /// `None` when the combined regex is too large to build; every pattern is then run.
+    set: Option<RegexSet>,
+}
+
+impl PatternSet {
+    /// Combines `patterns`, given in priority order.
+    pub fn new(patterns: Vec<DateTimePattern>) -> PatternSet {
+        let set = RegexSet::new(patterns.iter().map(|p| p.regex.as_str())).ok();
+        PatternSet { patterns, set }
+    }
+
+    /// The patterns in priority order.
+    pub fn patterns(&self) -> &[DateTimePattern] {
+        &self.patterns
+    }
+
+    /// Indices of the patterns that match somewhere in `line`, in priority order.
+    pub(crate) fn matching(&self, line: &[u8]) -> Vec<usize> {
+        match &self.set {
+            Some(set) => set.matches(line).into_iter().collect(),
+            None => (0..self.patterns.len()).collect(),
+        }
+    }
+}
+
+/// Splits a strftime format into literal runs and single `%` specifiers, each with its byte
+/// position, so errors can point at the offending part.
+fn spec_spans(fmt: &str) -> Vec<(usize, &str)> {
+    let mut spans = Vec::new();
+    let mut chars = fmt.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let mut end = start + c.len_utf8();
+        if c == '%' {
+            // Padding, precision and colon modifiers, then the specifier itself.
+            while let Some(&(i, m)) = chars.peek() {
+                end = i + m.len_utf8();
+                chars.next();
+                if !"-_0.:#123456789".contains(m) {
+                    break;
+                }
+            }
+        } else {
+            while let Some(&(i, m)) = chars.peek() {
+                if m == '%' {
+                    break;
+                }
+                end = i + m.len_utf8();
+                chars.next();
+            }
+        }
+        spans.push((start, &fmt[start..end]));
+    }
+    spans
+}
+
+/// Compiles a strftime format into a [`DateTimePattern`].
+///
+/// A leading `^` makes the pattern only match at the start of the text it searches.
+pub fn convert_dt_spec_regex(fmt: &str) -> Result<DateTimePattern, PatternError> {
+    let (mut regex, body) = match fmt.strip_prefix('^') {
+        Some(body) => (String::from("^"), body),
+        None => (String::new(), fmt),
+    };
+    let mut is_naive = true;
+    let mut zulu = fmt.ends_with('Z') && !fmt.ends_with("%Z");
+    let mut zone_name = false;
+    let mut epoch = false;
+    let mut has_year = false;
+    for (position, spec) in spec_spans(body) {
+        let position = position + fmt.len() - body.len();
+        let unsupported = || PatternError::UnsupportedSpec {
+            format: fmt.to_string(),
+            spec: spec.to_string(),
+            position,
+        };
+        // chrono keeps the fraction-without-dot specifiers internal, so match them by name.
+        if let "%3f" | "%6f" | "%9f" = spec {
+            regex.push_str(&format!(r"\d{{{}}}", &spec[1..2]));
+            continue;
+        }
+        for item in StrftimeItems::new(spec) {
+            match item {
+                Item::Literal(s) => regex.push_str(&regex::escape(s)),
+                Item::Space(_) => regex.push_str("\\s*"),
+                Item::OwnedLiteral(ref s) => regex.push_str(&regex::escape(s)),
+                Item::OwnedSpace(_) => regex.push_str("\\s*"),
+                Item::Numeric(spec, pad) => {
+                    use chrono::format::Numeric::*;
+                    if let Year | YearDiv100 | YearMod100 | IsoYear | IsoYearDiv100
+                    | IsoYearMod100 = spec
+                    {
+                        has_year = true;
+                    }
+                    let width = match spec {
+                        Year | IsoYear => 4,
+                        YearDiv100 | YearMod100 | IsoYearDiv100 | IsoYearMod100 | Month | Day
+                        | WeekFromSun | WeekFromMon | IsoWeek | Hour | Hour12 | Minute | Second => {
+                            2
+                        }
+                        NumDaysFromSun | WeekdayFromMon => 1,
+                        Ordinal => 3,
+                        Nanosecond => 9,
+                        Timestamp => {
+                            regex.push_str(EPOCH_REGEX);
+                            epoch = true;
+                            has_year = true;
+                            continue;
+                        }
+                        _ => return Err(unsupported()),
+                    };
+                    if pad == Pad::Space {
+                        regex.push_str(&format!("\\s{{0,{}}}\\d{{1,{}}}", width - 1, width));
+                    } else {
+                        regex.push_str(&format!("\\d{{{}}}", width));
+                    }
+                }
+                Item::Fixed(spec) => {
+                    use chrono::format::Fixed::*;
+                    match spec {
+                        ShortMonthName => regex.push_str(&format!("(?:{})", SHORT_MONTHS)),
+                        LongMonthName => regex.push_str(&format!("(?:{})", LONG_MONTHS)),
+                        ShortWeekdayName => regex.push_str(&format!("(?:{})", SHORT_WEEKDAYS)),
+                        LongWeekdayName => regex.push_str(&format!("(?:{})", LONG_WEEKDAYS)),
+                        LowerAmPm => regex.push_str(&format!("(?:{})", LOWER_AM_PM)),
+                        UpperAmPm => regex.push_str(&format!("(?:{})", UPPER_AM_PM)),
+                        Nanosecond => regex.push_str(FRACTION_REGEX),
+                        Nanosecond3 => regex.push_str(r"\.\d{3}"),
+                        Nanosecond6 => regex.push_str(r"\.\d{6}"),
+                        Nanosecond9 => regex.push_str(r"\.\d{9}"),
+                        TimezoneName => {
+                            if zone_name {
+                                regex.push_str(r"[A-Za-z]{1,5}");
+                            } else {
+                                regex.push_str(r"(?P<tzname>[A-Za-z]{1,5})");
+                            }
+                            is_naive = false;
+                            zone_name = true;
+                        }
+                        TimezoneOffsetColon => {
+                            is_naive = false;
+                            regex.push_str(r"[+-]\d{2}:\d{2}");
+                        }
+                        TimezoneOffsetColonZ => {
+                            regex.push_str(r"(?:Z|[+-]\d{2}:\d{2})");
+                            is_naive = false;
+                            zulu = true;
+                        }
+                        TimezoneOffset => {
+                            regex.push_str(r"[+-]\d{2}\d{2}");
+                            is_naive = false;
+                        }
+                        TimezoneOffsetZ => {
+                            regex.push_str(r"(?:Z|[+-]\d{2}\d{2})");
+                            is_naive = false;
+                            zulu = true;
+                        }
+                        RFC2822 => {
+                            let dt = format!(
+                                r"(?:{short_weekday}),\s+{two_digit}\s+{month}\s+{four_digit}\s+{two_digit}:{two_digit}:{two_digit} [+-]{two_digit}{two_digit}",
+                                short_weekday = SHORT_WEEKDAYS,
+                                month = SHORT_MONTHS,
+                                four_digit = FOUR_DIGITS,
+                                two_digit = TWO_DIGITS,
+                            );
+                            regex.push_str(&dt);
+                            is_naive = false;
+                            has_year = true;
+                        }
+                        RFC3339 => {
+                            let dt = format!(
+                                r"{four_digit}-{two_digit}-{two_digit}T{two_digit}:{two_digit}:{two_digit}{fraction}(?:Z|[+-]{two_digit}:{two_digit})",
+                                two_digit = TWO_DIGITS,
+                                four_digit = FOUR_DIGITS,
+                                fraction = FRACTION_REGEX,
+                            );
+                            regex.push_str(&dt);
+                            is_naive = false;
+                            has_year = true;
+                        }
+                        _ => return Err(unsupported()),
+                    }
+                }
+                Item::Error => {
+                    return Err(PatternError::InvalidSpec {
+                        format: fmt.to_string(),
+                        spec: spec.to_string(),
+                        position,
+                    })
+                }
+            }
+        }
+    }
+    let regex = Regex::new(&regex).map_err(|source| PatternError::Regex {
+        format: fmt.to_string(),
+        source,
+    })?;
+    Ok(DateTimePattern {
+        format: body.to_string(),
+        regex,
+        is_naive,
+        zulu,
+        zone_name,
+        epoch,
+        has_year,
+    })
+}
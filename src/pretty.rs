@@ -0,0 +1,285 @@
+//! Rendering structured records as readable text through a template.
+
+use serde_json::{Map, Value};
+
+use crate::error::TemplateError;
+use crate::json::lookup;
+use crate::logfmt::push_pairs;
+
+/// The template used when none is configured.
+pub const DEFAULT_TEMPLATE: &str = "{time} {level|lvl|severity:upper} {msg|message}";
+
+/// A line layout for structured records, such as `{time} {level:upper} {msg}`.
+///
+/// Each `{...}` is replaced by the first of its `|`-separated fields that the record has,
+/// where a field can be a dotted path such as `{log.level}`. The name `time` stands for
+/// whichever field the record's time was read from. A placeholder can end in `:upper` or
+/// `:lower` to change the case of its value, and `{{` and `}}` are literal braces.
+#[derive(Debug, Clone)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Text(String),
+    Field { keys: Vec<String>, case: Case },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Case {
+    Keep,
+    Upper,
+    Lower,
+}
+
+impl Template {
+    /// Parses a template such as `{time} {level:upper} {msg}`.
+    pub fn parse(spec: &str) -> Result<Template, TemplateError> {
+        let template = || spec.to_string();
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = spec.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut field = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => field.push(c),
+                            None => {
+                                return Err(TemplateError::Unclosed {
+                                    template: template(),
+                                })
+                            }
+                        }
+                    }
+                    let (keys, case) = match field.rsplit_once(':') {
+                        Some((keys, "upper")) => (keys, Case::Upper),
+                        Some((keys, "lower")) => (keys, Case::Lower),
+                        Some((_, modifier)) => {
+                            return Err(TemplateError::UnknownModifier {
+                                template: template(),
+                                modifier: modifier.to_string(),
+                            })
+                        }
+                        None => (field.as_str(), Case::Keep),
+                    };
+                    let keys: Vec<String> = keys.split('|').map(str::to_string).collect();
+                    if keys.iter().any(String::is_empty) {
+                        return Err(TemplateError::EmptyField {
+                            template: template(),
+                        });
+                    }
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Field { keys, case });
+                }
+                '}' => {
+                    return Err(TemplateError::Unmatched {
+                        template: template(),
+                    })
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template { parts })
+    }
+}
+
+/// Renders structured records as readable text.
+#[derive(Debug, Clone)]
+pub struct Pretty {
+    /// Where the template's fields go; the rest follow it as `key=value`.
+    pub template: Template,
+    /// Dim the fields the template does not place, using ANSI escapes.
+    pub dim: bool,
+}
+
+impl Pretty {
+    /// Renders `record` through the template, followed by every field the template did not
+    /// place as `key=value`.
+    ///
+    /// `time_key` is the field the record's time was read from. A placeholder the record has
+    /// no field for, or only a null one, is left out along with the literal text attached to
+    /// it and the whitespace after it, so `[{level}] {msg}` becomes just the message. Null
+    /// extra fields are left out too.
+    pub fn render(&self, record: &Map<String, Value>, time_key: Option<&str>) -> Vec<u8> {
+        let mut used: Vec<&str> = time_key.into_iter().collect();
+        let mut values = Vec::new();
+        for part in &self.template.parts {
+            let (keys, case) = match part {
+                Part::Text(_) => {
+                    values.push(None);
+                    continue;
+                }
+                Part::Field { keys, case } => (keys, case),
+            };
+            let found = keys.iter().find_map(|key| {
+                let path = match (key.as_str(), time_key) {
+                    ("time", Some(time_key)) => time_key,
+                    _ => key.as_str(),
+                };
+                lookup(record, path)
+                    .filter(|value| !value.is_null())
+                    .map(|value| (path, value))
+            });
+            values.push(found.map(|(path, value)| {
+                used.push(path);
+                let value = match value {
+                    Value::String(text) => text.clone(),
+                    value => value.to_string(),
+                };
+                match case {
+                    Case::Keep => value,
+                    Case::Upper => value.to_uppercase(),
+                    Case::Lower => value.to_lowercase(),
+                }
+            }));
+        }
+
+        // Whether the part at an index is a placeholder that was filled in.
+        let filled = |idx: usize| match self.template.parts.get(idx) {
+            Some(Part::Field { .. }) => Some(values[idx].is_some()),
+            _ => None,
+        };
+        let mut line = Line::default();
+        for (idx, part) in self.template.parts.iter().enumerate() {
+            match part {
+                Part::Text(text) => {
+                    let before = idx.checked_sub(1).and_then(filled);
+                    line.push_literal(text, before, filled(idx + 1));
+                }
+                Part::Field { .. } => line.push(values[idx].as_deref().unwrap_or_default()),
+            }
+        }
+        let mut out = line.out;
+        out.truncate(out.trim_end().len());
+
+        let mut extras = String::new();
+        push_pairs(&mut extras, record, "", &used, false);
+        if !extras.is_empty() {
+            if self.dim {
+                out.push_str("\x1b[2m");
+            }
+            if out.is_empty() {
+                out.push_str(&extras[1..]);
+            } else {
+                out.push_str(&extras);
+            }
+            if self.dim {
+                out.push_str("\x1b[0m");
+            }
+        }
+        out.into_bytes()
+    }
+}
+
+/// A line being rendered, which holds back whitespace between placeholders until text follows
+/// it, so nothing is doubled or left dangling around placeholders that are left out.
+#[derive(Default)]
+struct Line<'a> {
+    out: String,
+    separator: Option<&'a str>,
+}
+
+impl<'a> Line<'a> {
+    fn push(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if let Some(separator) = self.separator.take() {
+            if !self.out.is_empty() {
+                self.out.push_str(separator);
+            }
+        }
+        self.out.push_str(text);
+    }
+
+    /// Adds the literal text next to placeholders, leaving out what belongs to a missing one.
+    ///
+    /// `before` and `after` say whether the placeholders on either side were filled in, and
+    /// are `None` at the ends of the template. Text up to the first whitespace hugs the
+    /// placeholder before it and text after the last whitespace hugs the one after it, like
+    /// the brackets in `[{level}] `, while the whitespace run between them is a separator.
+    /// Text without whitespace between two placeholders joins them, so it needs both.
+    fn push_literal(&mut self, text: &'a str, before: Option<bool>, after: Option<bool>) {
+        let first = match text.find(char::is_whitespace) {
+            Some(first) => first,
+            None => {
+                if before != Some(false) && after != Some(false) {
+                    self.push(text);
+                }
+                return;
+            }
+        };
+        let last = text.trim_end_matches(|c: char| !c.is_whitespace()).len();
+        match before {
+            None => self.push(&text[..last]),
+            Some(filled) => {
+                if filled {
+                    self.push(&text[..first]);
+                }
+                self.separator.get_or_insert(&text[first..last]);
+            }
+        }
+        if after != Some(false) {
+            self.push(&text[last..]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(template: &str, record: Value) -> String {
+        let pretty = Pretty {
+            template: Template::parse(template).unwrap(),
+            dim: false,
+        };
+        let record = record.as_object().unwrap();
+        String::from_utf8(pretty.render(record, Some("ts"))).unwrap()
+    }
+
+    #[test]
+    fn fills_in_placeholders() {
+        let record = json!({"ts": "10:00", "level": "warn", "msg": "slow", "ms": 812});
+        assert_eq!(
+            render(DEFAULT_TEMPLATE, record.clone()),
+            "10:00 WARN slow ms=812"
+        );
+        assert_eq!(
+            render("{time} [{level:upper}] {msg}", record),
+            "10:00 [WARN] slow ms=812"
+        );
+    }
+
+    #[test]
+    fn leaves_out_missing_placeholders_with_their_text() {
+        let record = json!({"msg": "e"});
+        assert_eq!(
+            render(DEFAULT_TEMPLATE, json!({"ts": "10:00", "msg": "e"})),
+            "10:00 e"
+        );
+        assert_eq!(render("[{level:upper}] {msg}", record.clone()), "e");
+        assert_eq!(render("{time} [{level}] {msg}", record.clone()), "e");
+        assert_eq!(render("{msg} ({user}) done", record.clone()), "e done");
+        assert_eq!(render("{level}:{msg}", record.clone()), "e");
+        assert_eq!(render("{msg} - {user}", record), "e");
+    }
+}
@@ -0,0 +1,196 @@
+use chrono::{DateTime, FixedOffset};
+use std::io::{self, prelude::*, BufReader, SeekFrom};
+
+use logzen::{find_timestamps, ParseOptions, PatternSet};
+
+/// Below this many bytes the remaining range is cheaper to scan than to keep bisecting.
+const MIN_SPAN: u64 = 64 * 1024;
+/// Lines read after a probe offset while looking for one with a timestamp.
+const MAX_PROBE_LINES: usize = 256;
+
+/// Outcome of bisecting a file for the start of a time window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bisection {
+    /// Byte offset of a line at or before the first line inside the window.
+    Sorted(u64),
+    /// Probes came back out of order, so the file has to be scanned from the start.
+    Unsorted,
+}
+
+/// Finds where to start reading a time-sorted file so that lines before `since` are skipped.
+///
+/// Each probe seeks to a byte offset, resyncs to the next line and parses the first timestamp
+/// it finds with the configured patterns. The returned offset always points at the start of a
+/// line whose timestamp is before `since` (or at the start of the file), so the window filter
+/// still sees the header of any record that straddles the boundary.
+pub fn bisect<R: Read + Seek>(
+    file: &mut R,
+    since: &DateTime<FixedOffset>,
+    regex_list: &PatternSet,
+    options: &ParseOptions,
+) -> io::Result<Bisection> {
+    // Probes jump around the file, so they must not feed year inference for the real pass.
+    let options = options.clone();
+    let len = file.seek(SeekFrom::End(0))?;
+    let (mut lo, mut lo_ts) = match probe(file, 0, regex_list, &options)? {
+        Some((offset, ts)) if ts < *since => (offset, ts),
+        _ => return Ok(Bisection::Sorted(0)),
+    };
+    let mut hi = len;
+    let mut hi_ts: Option<DateTime<FixedOffset>> = None;
+    while hi - lo > MIN_SPAN {
+        let mid = lo + (hi - lo) / 2;
+        match probe(file, mid, regex_list, &options)? {
+            Some((offset, ts)) if offset < hi => {
+                if ts < lo_ts || hi_ts.is_some_and(|hi_ts| ts > hi_ts) {
+                    return Ok(Bisection::Unsorted);
+                }
+                if ts < *since {
+                    lo = offset;
+                    lo_ts = ts;
+                } else {
+                    hi = mid;
+                    hi_ts = Some(ts);
+                }
+            }
+            _ => hi = mid,
+        }
+    }
+    Ok(Bisection::Sorted(lo))
+}
+
+/// Returns the offset and first timestamp of the first timestamped line starting at or after
+/// `offset`.
+fn probe<R: Read + Seek>(
+    file: &mut R,
+    offset: u64,
+    regex_list: &PatternSet,
+    options: &ParseOptions,
+) -> io::Result<Option<(u64, DateTime<FixedOffset>)>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut reader = BufReader::new(file);
+    let mut position = offset;
+    let mut buf = Vec::new();
+    if offset > 0 {
+        // Landed mid-line: skip to the start of the next one.
+        position += reader.read_until(b'\n', &mut buf)? as u64;
+    }
+    options.years.restart();
+    for _ in 0..MAX_PROBE_LINES {
+        buf.clear();
+        let read = reader.read_until(b'\n', &mut buf)?;
+        if read == 0 {
+            break;
+        }
+        if let Some(m) = find_timestamps(&buf, regex_list, options).first() {
+            return Ok(Some((position, m.datetime)));
+        }
+        position += read as u64;
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+    use logzen::convert_dt_spec_regex;
+    use std::io::Cursor;
+
+    fn time(secs: i64) -> DateTime<FixedOffset> {
+        let start = Utc.with_ymd_and_hms(2026, 10, 15, 0, 0, 0).unwrap();
+        (start + Duration::seconds(secs)).fixed_offset()
+    }
+
+    /// One line per second, in the order of `seconds`.
+    fn log(seconds: impl Iterator<Item = i64>) -> String {
+        seconds
+            .map(|secs| format!("{} request handled\n", time(secs).to_rfc3339()))
+            .collect()
+    }
+
+    fn bisect_text(text: &str, since: &DateTime<FixedOffset>) -> Bisection {
+        let regex_list = PatternSet::new(vec![convert_dt_spec_regex("%+").unwrap()]);
+        let mut file = Cursor::new(text.as_bytes());
+        bisect(&mut file, since, &regex_list, &ParseOptions::default()).unwrap()
+    }
+
+    /// Checks that `offset` starts a line and that no line in the window comes before it.
+    fn assert_starts_before_window(text: &str, offset: u64, since: &DateTime<FixedOffset>) {
+        let offset = offset as usize;
+        assert!(offset == 0 || text.as_bytes()[offset - 1] == b'\n');
+        let mut position = 0;
+        for line in text.split_inclusive('\n') {
+            if position >= offset {
+                break;
+            }
+            if let Some(stamp) = line.split(' ').next() {
+                if let Ok(dt) = DateTime::parse_from_rfc3339(stamp) {
+                    assert!(dt < *since, "line at {} is in the window", position);
+                }
+            }
+            position += line.len();
+        }
+    }
+
+    #[test]
+    fn finds_the_window_in_sorted_input() {
+        let text = log(0..40_000);
+        let since = time(25_000);
+        let offset = match bisect_text(&text, &since) {
+            Bisection::Sorted(offset) => offset,
+            Bisection::Unsorted => panic!("sorted input was reported unsorted"),
+        };
+        assert_starts_before_window(&text, offset, &since);
+        let first_in_window = text.find(&since.to_rfc3339()).unwrap() as u64;
+        assert!(offset <= first_in_window && first_in_window - offset <= MIN_SPAN);
+    }
+
+    #[test]
+    fn gives_up_on_unsorted_input() {
+        let text = log((0..40_000).rev());
+        assert_eq!(bisect_text(&text, &time(50_000)), Bisection::Unsorted);
+    }
+
+    #[test]
+    fn starts_at_the_beginning_when_since_is_before_the_first_line() {
+        let text = log(100..40_000);
+        assert_eq!(bisect_text(&text, &time(0)), Bisection::Sorted(0));
+    }
+
+    #[test]
+    fn skips_to_the_end_when_since_is_after_the_last_line() {
+        let text = log(0..40_000);
+        let since = time(50_000);
+        let offset = match bisect_text(&text, &since) {
+            Bisection::Sorted(offset) => offset,
+            Bisection::Unsorted => panic!("sorted input was reported unsorted"),
+        };
+        assert_starts_before_window(&text, offset, &since);
+        assert!(text.len() as u64 - offset <= MIN_SPAN);
+    }
+
+    #[test]
+    fn steps_over_lines_without_timestamps() {
+        // Stack traces after every 500th line, some longer than a probe reads.
+        let mut text = String::new();
+        for secs in 0..40_000 {
+            text.push_str(&log(secs..secs + 1));
+            if secs % 500 == 0 {
+                let frames = if secs % 1_000 == 0 {
+                    20
+                } else {
+                    MAX_PROBE_LINES + 10
+                };
+                text.push_str(&"    at com.example.Handler.run(Handler.java:42)\n".repeat(frames));
+            }
+        }
+        let since = time(31_234);
+        let offset = match bisect_text(&text, &since) {
+            Bisection::Sorted(offset) => offset,
+            Bisection::Unsorted => panic!("sorted input was reported unsorted"),
+        };
+        assert_starts_before_window(&text, offset, &since);
+        assert!(offset > 0);
+    }
+}
@@ -0,0 +1,383 @@
+//! RFC 5424 and RFC 3164 syslog messages, read as records.
+
+use chrono::{DateTime, FixedOffset};
+use regex::bytes::Regex;
+use serde_json::{Map, Value};
+
+use crate::{
+    convert_dt_spec_regex, parse_timestamp, Conversion, DateTimePattern, ParseOptions, ParsedRecord,
+};
+
+/// Facility names by number, as used by syslog.conf.
+const FACILITIES: [&str; 24] = [
+    "kern",
+    "user",
+    "mail",
+    "daemon",
+    "auth",
+    "syslog",
+    "lpr",
+    "news",
+    "uucp",
+    "cron",
+    "authpriv",
+    "ftp",
+    "ntp",
+    "security",
+    "console",
+    "solaris-cron",
+    "local0",
+    "local1",
+    "local2",
+    "local3",
+    "local4",
+    "local5",
+    "local6",
+    "local7",
+];
+
+/// Severity names by number, from most to least severe.
+const SEVERITIES: [&str; 8] = [
+    "emerg", "alert", "crit", "err", "warning", "notice", "info", "debug",
+];
+
+/// The byte order mark an RFC 5424 message may start with.
+const BOM: &str = "\u{feff}";
+
+lazy_static::lazy_static! {
+    /// The RFC 5424 header up to the structured data.
+    static ref RFC5424: Regex =
+        Regex::new(r"^<(\d{1,3})>\d{1,2} (\S+) (\S+) (\S+) (\S+) (\S+) ").unwrap();
+    /// An RFC 3164 line, with or without its PRI as in `/var/log/syslog`, whose timestamp is
+    /// either BSD style or RFC 3339 as rsyslog writes by default.
+    static ref RFC3164: Regex = Regex::new(
+        r"^(?:<(\d{1,3})>)?([A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}|\d{4}-\d{2}-\d{2}T\S+) (\S+)(?: ([^\s:\[]+)(?:\[([^\]]*)\])?:)? ?"
+    )
+    .unwrap();
+}
+
+/// Reads syslog messages into records.
+///
+/// The PRI is decoded into `facility` and `severity` names, and the header into `time`,
+/// `hostname`, `app_name`, `procid` and `msgid`. RFC 5424 structured data becomes
+/// `structured_data`, an object of SD-IDs mapping to their parameters, and the rest of the
+/// line is `message`. Fields set to `-` are null, and lines without a PRI, as in
+/// `/var/log/syslog`, have no `facility` or `severity`. A BSD style `time` is written as
+/// ISO 8601 with its inferred year; only the line itself keeps the BSD layout.
+#[derive(Debug)]
+pub struct SyslogFormat {
+    rfc3339: DateTimePattern,
+    bsd: DateTimePattern,
+    /// Only keep messages at least this severe, where 0 is `emerg` and 7 is `debug`.
+    pub max_severity: Option<usize>,
+    /// Only keep messages from these apps, if any are given.
+    pub apps: Vec<String>,
+}
+
+impl Default for SyslogFormat {
+    fn default() -> SyslogFormat {
+        SyslogFormat {
+            rfc3339: convert_dt_spec_regex("%+").expect("%+ compiles"),
+            bsd: convert_dt_spec_regex("%b %e %H:%M:%S").expect("%b %e %H:%M:%S compiles"),
+            max_severity: None,
+            apps: Vec::new(),
+        }
+    }
+}
+
+/// The number of a severity given by name, such as `warning` or its short form `warn`, or by
+/// number.
+pub fn severity_level(name: &str) -> Option<usize> {
+    let name = name.to_ascii_lowercase();
+    let name = match name.as_str() {
+        "panic" => "emerg",
+        "error" => "err",
+        "warn" => "warning",
+        name => name,
+    };
+    match name.parse::<usize>() {
+        Ok(level) if level < SEVERITIES.len() => Some(level),
+        Ok(_) => None,
+        Err(_) => SEVERITIES.iter().position(|severity| *severity == name),
+    }
+}
+
+impl SyslogFormat {
+    /// Whether messages are filtered by severity or app. Lines that are not syslog messages
+    /// are then left out too.
+    pub fn filters(&self) -> bool {
+        self.max_severity.is_some() || !self.apps.is_empty()
+    }
+
+    /// Whether a record read by [`SyslogFormat::convert`] passes the severity and app filters.
+    /// A message without a PRI has no severity, so it fails a severity filter.
+    pub fn keeps(&self, record: &Map<String, Value>) -> bool {
+        let severe_enough = self.max_severity.is_none_or(|max| {
+            record
+                .get("severity")
+                .and_then(Value::as_str)
+                .and_then(|name| SEVERITIES.iter().position(|severity| *severity == name))
+                .is_some_and(|level| level <= max)
+        });
+        let from_app = self.apps.is_empty()
+            || record
+                .get("app_name")
+                .and_then(Value::as_str)
+                .is_some_and(|app| self.apps.iter().any(|wanted| wanted == app));
+        severe_enough && from_app
+    }
+
+    /// Reads `line` into a record and converts its timestamp, or returns `None` if it is not
+    /// a syslog message.
+    pub fn convert(
+        &self,
+        line: &[u8],
+        options: &ParseOptions,
+        conversion: &Conversion,
+    ) -> Option<ParsedRecord> {
+        let mut parsed = ParsedRecord {
+            record: Map::new(),
+            time: None,
+            time_span: 0..0,
+            time_text: String::new(),
+        };
+        let record = &mut parsed.record;
+        let message = if let Some(captures) = RFC5424.captures(line) {
+            insert_priority(record, captures.get(1)?.as_bytes())?;
+            let time = captures.get(2)?;
+            let (value, dt) = self.timestamp(time.as_bytes(), &self.rfc3339, options, conversion);
+            record.insert("time".to_string(), value);
+            if let Some(dt) = dt {
+                parsed.time = Some(dt);
+                parsed.time_span = time.range();
+                parsed.time_text = conversion.render(&dt, &self.rfc3339);
+            }
+            for (idx, key) in ["hostname", "app_name", "procid", "msgid"]
+                .iter()
+                .enumerate()
+            {
+                let value = captures.get(idx + 3)?.as_bytes();
+                record.insert(key.to_string(), nil_or_text(value));
+            }
+            let rest = &line[captures.get(0)?.end()..];
+            let (structured_data, rest) = structured_data(rest)?;
+            record.insert("structured_data".to_string(), structured_data);
+            let message = match rest {
+                [] => None,
+                [b' ', message @ ..] => Some(message),
+                _ => return None,
+            };
+            message.map(|message| {
+                let message = String::from_utf8_lossy(message);
+                message.strip_prefix(BOM).unwrap_or(&message).to_string()
+            })
+        } else {
+            let captures = RFC3164.captures(line)?;
+            if let Some(pri) = captures.get(1) {
+                insert_priority(record, pri.as_bytes())?;
+            }
+            let time = captures.get(2)?;
+            let pattern = if time.as_bytes()[0].is_ascii_digit() {
+                &self.rfc3339
+            } else {
+                &self.bsd
+            };
+            let (value, dt) = self.timestamp(time.as_bytes(), pattern, options, conversion);
+            record.insert("time".to_string(), value);
+            if let Some(dt) = dt {
+                parsed.time = Some(dt);
+                parsed.time_span = time.range();
+                parsed.time_text = conversion.render(&dt, pattern);
+            }
+            for (idx, key) in ["hostname", "app_name", "procid"].iter().enumerate() {
+                let value = captures.get(idx + 3).map_or(Value::Null, |value| {
+                    Value::String(String::from_utf8_lossy(value.as_bytes()).into_owned())
+                });
+                record.insert(key.to_string(), value);
+            }
+            let message = &line[captures.get(0)?.end()..];
+            Some(String::from_utf8_lossy(message).into_owned())
+        };
+        parsed.record.insert(
+            "message".to_string(),
+            message.map_or(Value::Null, Value::String),
+        );
+        Some(parsed)
+    }
+
+    /// The record value for a header timestamp, converted if it parses.
+    fn timestamp(
+        &self,
+        text: &[u8],
+        pattern: &DateTimePattern,
+        options: &ParseOptions,
+        conversion: &Conversion,
+    ) -> (Value, Option<DateTime<FixedOffset>>) {
+        let text = String::from_utf8_lossy(text);
+        if text == "-" {
+            return (Value::Null, None);
+        }
+        match parse_timestamp(&text, pattern, options) {
+            Some(dt) => (
+                Value::String(conversion.render_field(&dt, pattern)),
+                Some(dt),
+            ),
+            None => (Value::String(text.into_owned()), None),
+        }
+    }
+}
+
+/// Decodes a PRI into the `facility` and `severity` fields.
+fn insert_priority(record: &mut Map<String, Value>, pri: &[u8]) -> Option<()> {
+    let pri: usize = std::str::from_utf8(pri).ok()?.parse().ok()?;
+    let facility = FACILITIES.get(pri / 8)?;
+    record.insert("facility".to_string(), Value::from(*facility));
+    record.insert("severity".to_string(), Value::from(SEVERITIES[pri % 8]));
+    Some(())
+}
+
+fn nil_or_text(value: &[u8]) -> Value {
+    match value {
+        b"-" => Value::Null,
+        value => Value::String(String::from_utf8_lossy(value).into_owned()),
+    }
+}
+
+/// Reads RFC 5424 structured data such as `[id@1 key="value"][id2]` from the start of `text`,
+/// returning it with the rest of the text.
+fn structured_data(text: &[u8]) -> Option<(Value, &[u8])> {
+    if let Some(rest) = text.strip_prefix(b"-") {
+        return Some((Value::Null, rest));
+    }
+    let mut elements = Map::new();
+    let mut rest = text;
+    while let Some(element) = rest.strip_prefix(b"[") {
+        let id_end = element.iter().position(|&b| b == b' ' || b == b']')?;
+        let id = String::from_utf8_lossy(&element[..id_end]).into_owned();
+        let mut params = Map::new();
+        rest = &element[id_end..];
+        while let Some(param) = rest.strip_prefix(b" ") {
+            let name_end = param.iter().position(|&b| b == b'=')?;
+            let name = String::from_utf8_lossy(&param[..name_end]).into_owned();
+            let value = param[name_end + 1..].strip_prefix(b"\"")?;
+            // Inside a value, `\"`, `\\` and `\]` are escapes.
+            let mut unescaped = Vec::new();
+            let mut idx = 0;
+            loop {
+                match *value.get(idx)? {
+                    b'\\' if matches!(value.get(idx + 1), Some(b'"' | b'\\' | b']')) => {
+                        unescaped.push(value[idx + 1]);
+                        idx += 2;
+                    }
+                    b'"' => break,
+                    b => {
+                        unescaped.push(b);
+                        idx += 1;
+                    }
+                }
+            }
+            params.insert(
+                name,
+                Value::String(String::from_utf8_lossy(&unescaped).into_owned()),
+            );
+            rest = &value[idx + 1..];
+        }
+        rest = rest.strip_prefix(b"]")?;
+        elements.insert(id, Value::Object(params));
+    }
+    if elements.is_empty() {
+        return None;
+    }
+    Some((Value::Object(elements), rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OutputFormat, TargetZone};
+
+    fn convert(line: &str) -> Option<ParsedRecord> {
+        let conversion = Conversion {
+            zone: TargetZone::from_name("UTC").unwrap(),
+            output: OutputFormat::Input,
+        };
+        let mut options = ParseOptions::default();
+        options.years.start = Some(2026);
+        SyslogFormat::default().convert(line.as_bytes(), &options, &conversion)
+    }
+
+    #[test]
+    fn writes_bsd_times_with_their_year() {
+        let parsed = convert("<34>Oct 11 22:14:15 mymachine su: 'su root' failed").unwrap();
+        assert_eq!(parsed.record["time"], "2026-10-11T22:14:15+00:00");
+        assert_eq!(parsed.time_text, "Oct 11 22:14:15+00:00");
+        assert_eq!(parsed.time_span, 4..19);
+        assert_eq!(parsed.record["facility"], "auth");
+        assert_eq!(parsed.record["severity"], "crit");
+        assert_eq!(parsed.record["app_name"], "su");
+        assert_eq!(parsed.record["message"], "'su root' failed");
+    }
+
+    #[test]
+    fn reads_severities_by_name_or_number() {
+        assert_eq!(severity_level("warning"), Some(4));
+        assert_eq!(severity_level("WARN"), Some(4));
+        assert_eq!(severity_level("error"), Some(3));
+        assert_eq!(severity_level("7"), Some(7));
+        assert_eq!(severity_level("8"), None);
+        assert_eq!(severity_level("loud"), None);
+    }
+
+    #[test]
+    fn keeps_messages_by_severity_and_app() {
+        let format = SyslogFormat {
+            max_severity: Some(3),
+            apps: vec!["su".to_string()],
+            ..SyslogFormat::default()
+        };
+        let keeps = |line: &str| format.keeps(&convert(line).unwrap().record);
+        assert!(keeps("<34>Oct 11 22:14:15 host su: failed"));
+        // Severity 6, info.
+        assert!(!keeps("<38>Oct 11 22:14:15 host su: ok"));
+        assert!(!keeps("<34>Oct 11 22:14:15 host sshd[1]: failed"));
+        // No PRI, so no severity.
+        assert!(!keeps("Oct 11 22:14:15 host su: failed"));
+    }
+
+    #[test]
+    fn reads_rfc5424_headers_and_structured_data() {
+        let line = "<165>1 2026-10-15T22:14:15.003Z host app - ID47 [a@1 k=\"v \\\"q\\\" \\]\"][b@2] \u{feff}hello";
+        let record = convert(line).unwrap().record;
+        assert_eq!(record["facility"], "local4");
+        assert_eq!(record["severity"], "notice");
+        assert_eq!(record["time"], "2026-10-15T22:14:15.003+00:00");
+        assert_eq!(record["procid"], Value::Null);
+        assert_eq!(record["msgid"], "ID47");
+        assert_eq!(
+            record["structured_data"],
+            serde_json::json!({"a@1": {"k": "v \"q\" ]"}, "b@2": {}})
+        );
+        assert_eq!(record["message"], "hello");
+    }
+
+    #[test]
+    fn reads_structured_data() {
+        assert_eq!(
+            structured_data(b"- rest"),
+            Some((Value::Null, &b" rest"[..]))
+        );
+        let (data, rest) = structured_data(br#"[id k="a\\b" j="\x"] msg"#).unwrap();
+        assert_eq!(data, serde_json::json!({"id": {"k": "a\\b", "j": "\\x"}}));
+        assert_eq!(rest, b" msg");
+        assert!(structured_data(b"[id k=unquoted]").is_none());
+        assert!(structured_data(br#"[id k="unterminated]"#).is_none());
+        assert!(structured_data(b"[id").is_none());
+        assert!(structured_data(b"no data").is_none());
+    }
+
+    #[test]
+    fn leaves_other_lines_to_be_read_as_text() {
+        assert!(convert("2026-10-15 10:00:00 started").is_none());
+        assert!(convert("<999>Oct 11 22:14:15 host su: bad PRI").is_none());
+    }
+}
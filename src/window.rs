@@ -0,0 +1,197 @@
+//! Filtering lines by time with `--since` and `--until`.
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use regex::Regex;
+
+use crate::error::TimeExpressionError;
+use crate::{find_timestamps, ParseOptions, PatternSet, TargetZone};
+
+/// Date-only and date-time forms accepted by `--since`/`--until` in addition to the log
+/// patterns. They carry no offset and are read as wall-clock time in the target zone.
+const EXPRESSION_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+];
+
+/// An inclusive time range; either end may be open.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeWindow {
+    /// The earliest time kept.
+    pub since: Option<DateTime<FixedOffset>>,
+    /// The latest time kept.
+    pub until: Option<DateTime<FixedOffset>>,
+}
+
+impl TimeWindow {
+    /// Whether the window has neither end, so it keeps everything.
+    pub fn is_unbounded(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    /// Whether `dt` falls inside the window.
+    pub fn contains(&self, dt: &DateTime<FixedOffset>) -> bool {
+        self.since.is_none_or(|since| *dt >= since) && self.until.is_none_or(|until| *dt <= until)
+    }
+
+    /// Whether `dt` is past the end of the window.
+    pub fn is_after(&self, dt: &DateTime<FixedOffset>) -> bool {
+        self.until.is_some_and(|until| *dt > until)
+    }
+}
+
+/// Decides line by line whether output falls inside a [`TimeWindow`].
+///
+/// A line is judged by its first timestamp. Lines without one, such as stack trace
+/// continuations, take the time of the line before them so a record stays together.
+#[derive(Debug, Default)]
+pub struct WindowFilter {
+    window: TimeWindow,
+    last: Option<DateTime<FixedOffset>>,
+}
+
+impl WindowFilter {
+    /// A filter that has not seen a line yet.
+    pub fn new(window: TimeWindow) -> WindowFilter {
+        WindowFilter { window, last: None }
+    }
+
+    /// Whether to keep a line whose first timestamp is `first`.
+    pub fn keep(&mut self, first: Option<DateTime<FixedOffset>>) -> bool {
+        if self.window.is_unbounded() {
+            return true;
+        }
+        if first.is_some() {
+            self.last = first;
+        }
+        self.last.is_some_and(|dt| self.window.contains(&dt))
+    }
+}
+
+/// Parses a `--since`/`--until` value relative to `now`.
+///
+/// Accepts `now`, offsets such as `-2h` or `+30m` (units `s`, `m`, `h`, `d`, `w`),
+/// `today`/`yesterday`/`tomorrow` with an optional `HH:MM[:SS]`, a bare `HH:MM[:SS]` for today,
+/// anything the configured log patterns recognise, and `YYYY-MM-DD[ HH:MM[:SS]]`.
+pub fn parse_time_expr(
+    expr: &str,
+    now: DateTime<Utc>,
+    zone: &TargetZone,
+    regex_list: &PatternSet,
+    options: &ParseOptions,
+) -> Result<DateTime<FixedOffset>, TimeExpressionError> {
+    let expr = expr.trim();
+    let lower = expr.to_ascii_lowercase();
+    let today = zone.convert(&now).naive_local().date();
+    let unrecognised = || TimeExpressionError {
+        expr: expr.to_string(),
+    };
+
+    if lower == "now" {
+        return Ok(zone.convert(&now));
+    }
+    if let Some(offset) = parse_offset(&lower) {
+        let time = now.checked_add_signed(offset).ok_or_else(unrecognised)?;
+        return Ok(zone.convert(&time));
+    }
+
+    let mut words = lower.splitn(2, ' ');
+    let day = match words.next() {
+        Some("today") => Some(today),
+        Some("yesterday") => today.pred_opt(),
+        Some("tomorrow") => today.succ_opt(),
+        _ => None,
+    };
+    if let Some(day) = day {
+        let time = match words.next() {
+            Some(clock) => parse_clock(clock.trim()).ok_or_else(unrecognised)?,
+            None => NaiveTime::from_hms_opt(0, 0, 0).ok_or_else(unrecognised)?,
+        };
+        return zone.localize(&day.and_time(time)).ok_or_else(unrecognised);
+    }
+    if let Some(time) = parse_clock(&lower) {
+        return zone
+            .localize(&today.and_time(time))
+            .ok_or_else(unrecognised);
+    }
+
+    // The expression is parsed on its own, so it must not advance year inference for the log.
+    let options = options.clone();
+    options.years.restart();
+    if let [m] = find_timestamps(expr.as_bytes(), regex_list, &options).as_slice() {
+        if m.start == 0 && m.end == expr.len() {
+            return Ok(m.datetime);
+        }
+    }
+
+    let naive = EXPRESSION_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(expr, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(expr, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(unrecognised)?;
+    zone.localize(&naive).ok_or_else(unrecognised)
+}
+
+fn parse_offset(expr: &str) -> Option<Duration> {
+    lazy_static::lazy_static! {
+        static ref OFFSET: Regex = Regex::new(r"^([+-])\s*(\d+)\s*([smhdw])$").unwrap();
+    }
+    let captures = OFFSET.captures(expr)?;
+    let amount: i64 = captures[2].parse().ok()?;
+    let duration = match &captures[3] {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        _ => Duration::try_weeks(amount),
+    }?;
+    Some(if &captures[1] == "-" {
+        -duration
+    } else {
+        duration
+    })
+}
+
+fn parse_clock(clock: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(clock, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(clock, "%H:%M"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_dt_spec_regex;
+    use chrono::TimeZone;
+
+    fn parse(expr: &str) -> Result<DateTime<FixedOffset>, TimeExpressionError> {
+        let regex_list = PatternSet::new(vec![convert_dt_spec_regex("%+").unwrap()]);
+        let now = Utc.with_ymd_and_hms(2026, 10, 15, 10, 0, 0).unwrap();
+        let zone = TargetZone::from_name("UTC").unwrap();
+        parse_time_expr(expr, now, &zone, &regex_list, &ParseOptions::default())
+    }
+
+    #[test]
+    fn reads_offsets_from_now() {
+        assert_eq!(
+            parse("-2h").unwrap().to_rfc3339(),
+            "2026-10-15T08:00:00+00:00"
+        );
+        assert_eq!(
+            parse("+1w").unwrap().to_rfc3339(),
+            "2026-10-22T10:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn rejects_offsets_out_of_range() {
+        for expr in ["-99999999999999w", "-999999999d", "+99999999999999999999s"] {
+            assert_eq!(parse(expr).unwrap_err().expr, expr);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use chrono::{
+    DateTime, Datelike, FixedOffset, Local, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeZone,
+};
+use chrono_tz::{OffsetName, Tz};
+use std::collections::HashMap;
+
+use crate::error::ZoneError;
+
+/// Timezone abbreviations understood by `%Z` and the zone each stands for.
+///
+/// Some abbreviations are shared: `CST` is also China Standard Time, `IST` is also used in
+/// Ireland and Israel, and `AST` in Arabia. They resolve to the zone listed here, and
+/// `--tz-abbrev` points them at another one, such as `IST=Europe/Dublin`.
+pub const ZONE_ABBREVIATIONS: &[(&str, Tz)] = &[
+    ("UTC", Tz::UTC),
+    ("GMT", Tz::GMT),
+    ("Z", Tz::UTC),
+    ("PST", Tz::America__Los_Angeles),
+    ("PDT", Tz::America__Los_Angeles),
+    ("MST", Tz::America__Denver),
+    ("MDT", Tz::America__Denver),
+    ("CST", Tz::America__Chicago),
+    ("CDT", Tz::America__Chicago),
+    ("EST", Tz::America__New_York),
+    ("EDT", Tz::America__New_York),
+    ("AKST", Tz::America__Anchorage),
+    ("AKDT", Tz::America__Anchorage),
+    ("HST", Tz::Pacific__Honolulu),
+    ("AST", Tz::America__Halifax),
+    ("ADT", Tz::America__Halifax),
+    ("NST", Tz::America__St_Johns),
+    ("NDT", Tz::America__St_Johns),
+    ("WET", Tz::Europe__Lisbon),
+    ("WEST", Tz::Europe__Lisbon),
+    ("BST", Tz::Europe__London),
+    ("IST", Tz::Asia__Kolkata),
+    ("CET", Tz::Europe__Paris),
+    ("CEST", Tz::Europe__Paris),
+    ("EET", Tz::Europe__Athens),
+    ("EEST", Tz::Europe__Athens),
+    ("MSK", Tz::Europe__Moscow),
+    ("PKT", Tz::Asia__Karachi),
+    ("HKT", Tz::Asia__Hong_Kong),
+    ("SGT", Tz::Asia__Singapore),
+    ("JST", Tz::Asia__Tokyo),
+    ("KST", Tz::Asia__Seoul),
+    ("AWST", Tz::Australia__Perth),
+    ("ACST", Tz::Australia__Adelaide),
+    ("ACDT", Tz::Australia__Adelaide),
+    ("AEST", Tz::Australia__Sydney),
+    ("AEDT", Tz::Australia__Sydney),
+    ("NZST", Tz::Pacific__Auckland),
+    ("NZDT", Tz::Pacific__Auckland),
+    ("WAT", Tz::Africa__Lagos),
+    ("CAT", Tz::Africa__Maputo),
+    ("EAT", Tz::Africa__Nairobi),
+    ("SAST", Tz::Africa__Johannesburg),
+    ("BRT", Tz::America__Sao_Paulo),
+    ("ART", Tz::America__Argentina__Buenos_Aires),
+];
+
+/// Zone every matched timestamp is converted into before being rendered.
+#[derive(Debug, Clone, Copy)]
+pub enum TargetZone {
+    /// The system's zone.
+    Local,
+    /// An IANA zone.
+    Named(Tz),
+}
+
+impl TargetZone {
+    /// Parses an IANA zone name such as `Europe/Berlin` or `UTC`, or `local` for the
+    /// system zone.
+    pub fn from_name(name: &str) -> Result<TargetZone, ZoneError> {
+        if name.eq_ignore_ascii_case("local") {
+            return Ok(TargetZone::Local);
+        }
+        name.parse::<Tz>()
+            .map(TargetZone::Named)
+            .map_err(|_| ZoneError::UnknownZone(name.to_string()))
+    }
+
+    /// Converts `dt` into this zone, resolving the offset (and DST) for that instant.
+    pub fn convert<T: TimeZone>(&self, dt: &DateTime<T>) -> DateTime<FixedOffset> {
+        match self {
+            TargetZone::Local => fix_offset(dt.with_timezone(&Local)),
+            TargetZone::Named(tz) => fix_offset(dt.with_timezone(tz)),
+        }
+    }
+
+    /// Interprets a wall-clock time in this zone, taking the earlier instant if it is ambiguous.
+    pub fn localize(&self, naive: &NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+        match self {
+            TargetZone::Local => Local.from_local_datetime(naive).earliest().map(fix_offset),
+            TargetZone::Named(tz) => tz.from_local_datetime(naive).earliest().map(fix_offset),
+        }
+    }
+
+    /// Formats `dt` in this zone, so `%Z` renders the zone's own abbreviation where it has one.
+    pub fn format(&self, dt: &DateTime<FixedOffset>, format: &str) -> String {
+        match self {
+            TargetZone::Local => dt.with_timezone(&Local).format(format).to_string(),
+            TargetZone::Named(tz) => dt.with_timezone(tz).format(format).to_string(),
+        }
+    }
+}
+
+pub(crate) fn fix_offset<T: TimeZone>(dt: DateTime<T>) -> DateTime<FixedOffset> {
+    let offset = dt.offset().fix();
+    dt.with_timezone(&offset)
+}
+
+/// Parses an `ABBR=ZONE` override such as `IST=Europe/Dublin`.
+pub fn parse_abbreviation_override(value: &str) -> Result<(String, Tz), ZoneError> {
+    let mut parts = value.splitn(2, '=');
+    match (parts.next(), parts.next()) {
+        (Some(abbr), Some(zone)) if !abbr.is_empty() => {
+            let tz = zone
+                .parse::<Tz>()
+                .map_err(|_| ZoneError::UnknownZone(zone.to_string()))?;
+            Ok((abbr.to_ascii_uppercase(), tz))
+        }
+        _ => Err(ZoneError::InvalidOverride(value.to_string())),
+    }
+}
+
+/// Resolves `%Z` abbreviations through [`ZONE_ABBREVIATIONS`] and any user overrides.
+#[derive(Debug, Clone, Default)]
+pub struct ZoneAbbreviations {
+    /// Zones chosen for abbreviations, keyed by upper-case abbreviation.
+    pub overrides: HashMap<String, Tz>,
+}
+
+impl ZoneAbbreviations {
+    /// Returns the zone `abbr` stands for, preferring user overrides.
+    pub fn lookup(&self, abbr: &str) -> Option<Tz> {
+        let abbr = abbr.to_ascii_uppercase();
+        if let Some(tz) = self.overrides.get(&abbr) {
+            return Some(*tz);
+        }
+        ZONE_ABBREVIATIONS
+            .iter()
+            .find(|(name, _)| *name == abbr)
+            .map(|(_, tz)| *tz)
+    }
+
+    /// Interprets `naive` as a wall-clock time labelled with `abbr`.
+    ///
+    /// The abbreviation pins the offset: `PST` stays at -08:00 even in July, and a repeated
+    /// hour at a DST fall-back is resolved to whichever side carries that abbreviation.
+    pub fn resolve(&self, abbr: &str, naive: &NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+        let tz = self.lookup(abbr)?;
+        let candidates = match tz.from_local_datetime(naive) {
+            LocalResult::Single(dt) => vec![dt],
+            LocalResult::Ambiguous(a, b) => vec![a, b],
+            LocalResult::None => vec![],
+        };
+        let is_named = |name: &str| name.eq_ignore_ascii_case(abbr);
+        if let Some(dt) = candidates
+            .iter()
+            .find(|dt| is_named(dt.offset().abbreviation()))
+        {
+            return Some(fix_offset(*dt));
+        }
+        for month in &[1, 7] {
+            let probe = NaiveDate::from_ymd_opt(naive.year(), *month, 1)?.and_hms_opt(0, 0, 0)?;
+            let offset = tz.offset_from_utc_datetime(&probe);
+            if is_named(offset.abbreviation()) {
+                return offset.fix().from_local_datetime(naive).single();
+            }
+        }
+        candidates.into_iter().next().map(fix_offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn abbreviations_name_their_zones() {
+        for (abbr, tz) in ZONE_ABBREVIATIONS {
+            if *abbr == "Z" {
+                continue;
+            }
+            let used = [1, 7].iter().any(|month| {
+                let probe = NaiveDate::from_ymd_opt(2026, *month, 1)
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+                    .unwrap();
+                // tzdata writes some zones' abbreviations as bare offsets such as `+08`.
+                let offset = tz.offset_from_utc_datetime(&probe);
+                let name = offset.abbreviation();
+                name == *abbr || name.starts_with(['+', '-'])
+            });
+            assert!(used, "{} does not use {}", tz, abbr);
+        }
+    }
+
+    #[test]
+    fn shared_abbreviations_take_the_listed_zone_unless_overridden() {
+        let naive = NaiveDate::from_ymd_opt(2026, 7, 1)
+            .and_then(|date| date.and_hms_opt(12, 0, 0))
+            .unwrap();
+        let mut abbreviations = ZoneAbbreviations::default();
+        let resolve = |abbreviations: &ZoneAbbreviations| {
+            abbreviations.resolve("IST", &naive).unwrap().to_rfc3339()
+        };
+        assert_eq!(resolve(&abbreviations), "2026-07-01T12:00:00+05:30");
+        let (abbr, tz) = parse_abbreviation_override("ist=Europe/Dublin").unwrap();
+        abbreviations.overrides.insert(abbr, tz);
+        assert_eq!(resolve(&abbreviations), "2026-07-01T12:00:00+01:00");
+    }
+}